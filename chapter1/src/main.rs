@@ -1,27 +1,17 @@
-use std::fs::{self, File};
-use std::io::prelude::*;
-use std::io::LineWriter;
+use raytracer::{save, Framebuffer, Vec3};
 
 fn main() -> std::io::Result<()>{
     let nx = 200;
     let ny = 100;
 
-    let file = File::create("test.ppm")?;
-    let mut file = LineWriter::new(file);
-    file.write_all(b"P3\r\n")?;
-    file.write_all(format!("{} {}\r\n", nx, ny).as_bytes())?;
-    file.write_all(b"255\r\n")?;
+    let mut image = Framebuffer::new(nx, ny);
     for j in (0..ny).rev() {
         for i in 0..nx {
             let r = i as f32 / nx as f32;
             let g = j as f32 / ny as f32;
-            let b = 0.2 as f32;
-            let ir = (255.99 * r).trunc() as u8;
-            let ig = (255.99 * g).trunc() as u8;
-            let ib = (255.99 * b).trunc() as u8;
-            file.write_all(format!("{} {} {}\r\n", ir, ig, ib).as_bytes());
+            let b = 0.2_f32;
+            image.set(i, ny - 1 - j, Vec3::new(r, g, b));
         }
     }
-    file.flush()?;
-    Ok(())
+    save(&image, "test.ppm")
 }
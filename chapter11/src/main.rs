@@ -1,67 +1,356 @@
-mod myvec;
-mod ray;
-mod hitable;
-mod camera;
-mod material;
-
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::LineWriter;
-
-use ray::Ray;
-use myvec::Vec3;
-use hitable::{HitableList, Sphere, color, random_scene};
-use camera::Camera;
-use material::{Lambertian, Metal, Dielectric};
-use rand::{Rng, random};
-use std::rc::Rc;
-
-fn main() -> std::io::Result<()>{
-    let nx = 200;
-    let ny = 100;
-    let ns = 10;
-
-    let file = File::create("test2.ppm")?;
-    let mut file = LineWriter::new(file);
-    file.write_all(b"P3\r\n")?;
-    file.write_all(format!("{} {}\r\n", nx, ny).as_bytes())?;
-    file.write_all(b"255\r\n")?;
- 
-    let hitables = random_scene();
-   
-    let lookfrom = Vec3::new(13.0, 2.0, 3.0);
-    let lookat = Vec3::new(0., 0., 0.);
-    let vup = Vec3::new(0., 1., 0.);
-    let dist_to_focus = 10.0;
-    let aperture = 0.1;
-
-    let camera = Camera::new(
-        lookfrom, lookat, vup, 20.0, nx as f32 / ny as f32, aperture, dist_to_focus);
-    
-    let mut rng = rand::thread_rng();
-    
-    for j in (0..ny).rev() {
-        for i in 0..nx {
-            let mut col = Vec3::new(0., 0., 0.);
-            for s in 0..ns {
-                let random1 = rng.gen_range(0.0..1.0) as f32;
-                let random2 = rng.gen_range(0.0..1.0) as f32;
-                let u = (i as f32 + random1) / nx as f32;
-                let v = (j as f32 + random2) / ny as f32;
-                let r = camera.get_ray(u, v);
-                let p = r.point_at_paramter(2.0);
-                col += color(&r, &hitables,0);                
+mod progress;
+
+use clap::builder::RangedU64ValueParser;
+use clap::Parser;
+use raytracer::checkpoint;
+use raytracer::{denoise, load_scene, render_aovs, render_film, render_pass, save, save_as, save_with, AdaptiveSettings,
+                Aov, Background, BvhNode, Camera, Checkpoint, DenoiseSettings, Film, Filter, Framebuffer, Hitable,
+                ImageFormat, OutputSettings, SamplerKind, Scene, ToneMap, Vec3};
+use progress::ProgressBar;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Render the "Ray Tracing in One Weekend" final scene, or a TOML scene file.
+#[derive(Parser)]
+struct Args {
+    /// Built-in scene name (random, bouncing, cornell, smoke) or path to a TOML scene file
+    #[arg(long, default_value = "random")]
+    scene: String,
+    /// Output image; the extension picks the format (ppm, png, hdr, pfm) unless --format is given
+    #[arg(short, long, default_value = "test2.ppm")]
+    output: PathBuf,
+    /// Format of the output image: ppm, ppm-ascii, png, hdr or pfm
+    #[arg(long, value_parser = parse_format)]
+    format: Option<ImageFormat>,
+    /// Image width in pixels
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    width: Option<usize>,
+    /// Image height in pixels
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    height: Option<usize>,
+    /// Samples per pixel
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    spp: Option<usize>,
+    /// Keep sampling pixels whose relative error is above this threshold
+    #[arg(long, value_parser = parse_threshold)]
+    adaptive: Option<f32>,
+    /// Most samples per pixel with adaptive sampling (default 16 times --spp)
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    max_spp: Option<usize>,
+    /// Also write how many samples each pixel got, as a gray image
+    #[arg(long)]
+    sample_map: Option<PathBuf>,
+    /// Also write a feature of the first hits as NAME=PATH, where NAME is albedo, normal, depth,
+    /// position, material-id or object-id; pfm keeps the values exactly, hdr only if none are negative
+    /// (so not for normal or position)
+    #[arg(long, value_parser = parse_aov, value_name = "NAME=PATH")]
+    aov: Vec<(Aov, PathBuf)>,
+    /// Smooth away noise in the final image, guided by its albedo, normals and depth
+    #[arg(long)]
+    denoise: bool,
+    /// How much brightness differences the denoiser smooths over (default 1)
+    #[arg(long, requires = "denoise", value_parser = parse_strength)]
+    denoise_strength: Option<f32>,
+    /// Denoiser passes, each reaching twice as far as the last, from 1 to 16 (default 5)
+    #[arg(long, requires = "denoise", value_parser = RangedU64ValueParser::<usize>::new().range(1..=16))]
+    denoise_iterations: Option<usize>,
+    /// Render progressively, in this many passes of --spp samples each (default: one pass, or with
+    /// --adaptive as many as it takes to reach --max-spp)
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    passes: Option<usize>,
+    /// Write the image so far after every N passes
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    write_every_passes: Option<usize>,
+    /// Write the image so far after the first pass that ends SECS seconds after the last write
+    #[arg(long, value_name = "SECS")]
+    write_every_secs: Option<f32>,
+    /// Save the accumulated film here whenever the image is written
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Continue the progressive render saved in --checkpoint
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+    /// Maximum number of bounces per path
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// Bounces after which Russian roulette may end dim paths
+    #[arg(long)]
+    roulette_depth: Option<usize>,
+    /// Color of rays that miss everything: sky, black, or r,g,b
+    #[arg(long, value_parser = parse_background)]
+    background: Option<Background>,
+    /// Pixel filter: box, tent, gaussian, mitchell or lanczos, optionally with a radius as name=radius
+    #[arg(long, value_parser = parse_filter)]
+    filter: Option<Filter>,
+    /// Sample generator: independent, stratified, halton, sobol or blue-noise
+    #[arg(long, value_parser = parse_sampler)]
+    sampler: Option<SamplerKind>,
+    /// Random seed; the same seed gives the same image
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Worker threads (0 = one per core)
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Camera position, as x,y,z
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    lookfrom: Option<Vec3>,
+    /// Point the camera looks at, as x,y,z
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    lookat: Option<Vec3>,
+    /// Camera up direction, as x,y,z
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    vup: Option<Vec3>,
+    /// Vertical field of view in degrees
+    #[arg(long)]
+    vfov: Option<f32>,
+    /// Lens diameter; 0 disables depth of field
+    #[arg(long)]
+    aperture: Option<f32>,
+    /// Distance to the plane in focus
+    #[arg(long)]
+    focus_dist: Option<f32>,
+    /// Time at which the shutter opens
+    #[arg(long, allow_hyphen_values = true)]
+    shutter_open: Option<f32>,
+    /// Time at which the shutter closes; later than open gives motion blur
+    #[arg(long, allow_hyphen_values = true)]
+    shutter_close: Option<f32>,
+    /// Brightness adjustment in stops
+    #[arg(long, allow_hyphen_values = true)]
+    exposure: Option<f32>,
+    /// Tone mapping: clamp, reinhard, reinhard-extended[=white], aces or uncharted2
+    #[arg(long, value_parser = ToneMap::parse)]
+    tonemap: Option<ToneMap>,
+    /// Add grain before rounding to 8 bits, hiding banding
+    #[arg(long)]
+    dither: bool,
+    /// Do not draw the progress bar
+    #[arg(long)]
+    quiet: bool,
+}
+
+fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let parts: Vec<&str> = s.split(',').collect();
+    if parts.len() != 3 {
+        return Err(format!("expected x,y,z but got `{}`", s));
+    }
+    let mut v = [0.0; 3];
+    for (value, part) in v.iter_mut().zip(parts) {
+        *value = part.trim().parse().map_err(|_| format!("`{}` is not a number", part))?;
+    }
+    Ok(Vec3::new(v[0], v[1], v[2]))
+}
+
+fn parse_background(s: &str) -> Result<Background, String> {
+    match s {
+        "sky" => Ok(Background::Sky),
+        "black" => Ok(Background::Solid(Vec3::default())),
+        _ => parse_vec3(s).map(Background::Solid),
+    }
+}
+
+fn parse_filter(s: &str) -> Result<Filter, String> {
+    let (name, radius) = match s.split_once('=') {
+        Some((name, radius)) => (name, Some(radius)),
+        None => (s, None),
+    };
+    let filter = Filter::named(name).ok_or_else(|| format!("unknown filter `{}`", name))?;
+    match radius {
+        Some(radius) => {
+            let radius: f32 = radius.parse().map_err(|_| format!("`{}` is not a number", radius))?;
+            filter.with_radius(radius).ok_or_else(|| format!("filter radius must be finite and positive, not {}", radius))
+        }
+        None => Ok(filter),
+    }
+}
+
+fn parse_threshold(s: &str) -> Result<f32, String> {
+    let threshold: f32 = s.parse().map_err(|_| format!("`{}` is not a number", s))?;
+    if threshold.is_finite() && threshold >= 0.0 {
+        Ok(threshold)
+    } else {
+        Err(format!("adaptive threshold must be finite and not negative, not {}", threshold))
+    }
+}
+
+fn parse_strength(s: &str) -> Result<f32, String> {
+    let strength: f32 = s.parse().map_err(|_| format!("`{}` is not a number", s))?;
+    if strength.is_finite() && strength > 0.0 {
+        Ok(strength)
+    } else {
+        Err(format!("denoise strength must be finite and positive, not {}", strength))
+    }
+}
+
+fn parse_sampler(s: &str) -> Result<SamplerKind, String> {
+    SamplerKind::named(s).ok_or_else(|| format!("unknown sampler `{}`", s))
+}
+
+fn parse_aov(s: &str) -> Result<(Aov, PathBuf), String> {
+    let (name, path) = s.split_once('=').ok_or_else(|| format!("expected NAME=PATH but got `{}`", s))?;
+    let aov = Aov::named(name).ok_or_else(|| format!("unknown output variable `{}`", name))?;
+    let path = PathBuf::from(path);
+    if aov.is_signed() && ImageFormat::from_path(&path).ok() == Some(ImageFormat::Hdr) {
+        return Err(format!("hdr cannot store the negative values of `{}`; use pfm", name));
+    }
+    Ok((aov, path))
+}
+
+fn parse_format(s: &str) -> Result<ImageFormat, String> {
+    ImageFormat::named(s).ok_or_else(|| format!("unknown image format `{}`", s))
+}
+
+/// Writes the final image to `--output`, in the `--format` given or the one
+/// its extension names.
+fn write_output(image: &Framebuffer, args: &Args, output: &OutputSettings) -> io::Result<()> {
+    match args.format {
+        Some(format) => save_as(image, &args.output, format, output),
+        None => save_with(image, &args.output, output),
+    }
+}
+
+/// Loads the scene, along with its source for checkpoint fingerprints: the
+/// name of a built-in scene or the bytes of a scene file.
+fn load(args: &Args) -> io::Result<(Scene, Vec<u8>)> {
+    let seed = args.seed.unwrap_or_default();
+    if let Some(scene) = Scene::builtin(&args.scene, seed) {
+        return Ok((scene, args.scene.clone().into_bytes()));
+    }
+    let scene = load_scene(&args.scene).map_err(|err| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", args.scene, err))
+    })?;
+    Ok((scene, fs::read(&args.scene)?))
+}
+
+/// Writes the image of a progressive render so far, and its checkpoint.
+fn write_progress(args: &Args, scene: &Scene, checkpoint: &Checkpoint, fingerprint: &str) -> io::Result<()> {
+    if let Some(path) = &args.checkpoint {
+        checkpoint.save(path, fingerprint)?;
+    }
+    write_output(&checkpoint.film.to_framebuffer(), args, &scene.output)
+}
+
+/// Renders the passes that the checkpoint, if resuming, does not hold yet,
+/// up to `--passes` or by default all that the render settings call for,
+/// writing the image along the way as asked. `source` is the scene's, as
+/// `load` returns it.
+fn render_progressive(args: &Args, scene: &Scene, source: &[u8], world: &dyn Hitable, camera: &Camera,
+                      progress: &ProgressBar) -> io::Result<Film> {
+    let settings = &scene.settings;
+    let fingerprint = checkpoint::fingerprint(settings, &scene.camera, source);
+    let passes = args.passes.unwrap_or(settings.passes());
+    let mut checkpoint = match &args.checkpoint {
+        Some(path) if args.resume => Checkpoint::load(path, &fingerprint).map_err(|err| {
+            io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+        })?,
+        _ => Checkpoint { passes: 0, film: Film::new(settings.nx, settings.ny, settings.filter) },
+    };
+    let first = checkpoint.passes;
+    let (mut written, mut written_at) = (first, Instant::now());
+    while checkpoint.passes < passes {
+        let pass = checkpoint.passes - first;
+        let added = render_pass(world, &scene.lights, camera, settings, &mut checkpoint.film, |done, total| {
+            if !args.quiet {
+                progress.update(pass * total + done, (passes - first) * total);
+            }
+        });
+        checkpoint.passes += 1;
+        if added == 0 {
+            // Adaptive sampling has nothing left to refine.
+            if !args.quiet {
+                progress.update(1, 1);
             }
-            col /= ns as f32;
-            col = Vec3::new(col.x.sqrt(), col.y.sqrt(), col.z.sqrt());
-            col *= 255.99;
-            let ir = col.x.trunc() as u8;
-            let ig = col.y.trunc() as u8;
-            let ib = col.z.trunc() as u8;
-            file.write_all(format!("{} {} {}\r\n", ir, ig, ib).as_bytes());
+            break;
         }
+        let due = args.write_every_passes.is_some_and(|n| checkpoint.passes - written >= n)
+            || args.write_every_secs.is_some_and(|secs| written_at.elapsed().as_secs_f32() >= secs);
+        if due && checkpoint.passes < passes {
+            write_progress(args, scene, &checkpoint, &fingerprint)?;
+            written = checkpoint.passes;
+            written_at = Instant::now();
+        }
+    }
+    if let Some(path) = &args.checkpoint {
+        checkpoint.save(path, &fingerprint)?;
     }
+    Ok(checkpoint.film)
+}
 
-    file.flush()?;
-    Ok(())
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    let (mut scene, source) = load(&args)?;
+    for warning in scene.warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+
+    let settings = &mut scene.settings;
+    settings.nx = args.width.unwrap_or(settings.nx);
+    settings.ny = args.height.unwrap_or(settings.ny);
+    settings.ns = args.spp.unwrap_or(settings.ns);
+    if let Some(threshold) = args.adaptive {
+        let max_ns = settings.adaptive.map_or(16 * settings.ns, |adaptive| adaptive.max_ns);
+        settings.adaptive = Some(AdaptiveSettings { threshold, max_ns });
+    }
+    if let (Some(adaptive), Some(max_ns)) = (settings.adaptive.as_mut(), args.max_spp) {
+        adaptive.max_ns = max_ns;
+    }
+    settings.integrator.max_depth = args.max_depth.unwrap_or(settings.integrator.max_depth);
+    settings.integrator.roulette_depth = args.roulette_depth.unwrap_or(settings.integrator.roulette_depth);
+    settings.integrator.background = args.background.unwrap_or(settings.integrator.background);
+    settings.filter = args.filter.unwrap_or(settings.filter);
+    settings.sampler = args.sampler.unwrap_or(settings.sampler);
+    settings.seed = args.seed.unwrap_or(settings.seed);
+    settings.threads = args.threads.unwrap_or(settings.threads);
+
+    let camera = &mut scene.camera;
+    camera.lookfrom = args.lookfrom.unwrap_or(camera.lookfrom);
+    camera.lookat = args.lookat.unwrap_or(camera.lookat);
+    camera.vup = args.vup.unwrap_or(camera.vup);
+    camera.vfov = args.vfov.unwrap_or(camera.vfov);
+    camera.aperture = args.aperture.unwrap_or(camera.aperture);
+    camera.focus_dist = args.focus_dist.unwrap_or(camera.focus_dist);
+    camera.shutter_open = args.shutter_open.unwrap_or(camera.shutter_open);
+    camera.shutter_close = args.shutter_close.unwrap_or(camera.shutter_close);
+
+    let output = &mut scene.output;
+    output.exposure = args.exposure.unwrap_or(output.exposure);
+    output.tone_map = args.tonemap.unwrap_or(output.tone_map);
+    output.dither |= args.dither;
+
+    let world = BvhNode::new(mem::take(&mut scene.world).tagged(), scene.camera.shutter_open, scene.camera.shutter_close);
+    let settings = &scene.settings;
+    let camera = scene.camera.build(settings.nx as f32 / settings.ny as f32);
+
+    let progress = ProgressBar::new();
+    let film = if args.passes.is_some() || args.checkpoint.is_some() {
+        render_progressive(&args, &scene, &source, &world, &camera, &progress)?
+    } else {
+        render_film(&world, &scene.lights, &camera, settings, |done, total| {
+            if !args.quiet {
+                progress.update(done, total);
+            }
+        })
+    };
+    if let Some(path) = &args.sample_map {
+        save(&film.sample_map(), path)?;
+    }
+    let mut image = film.to_framebuffer();
+    if !args.aov.is_empty() || args.denoise {
+        let aovs = render_aovs(&world, &camera, settings, &film);
+        for (aov, path) in args.aov.iter() {
+            save(&aovs.image(*aov), path)?;
+        }
+        if args.denoise {
+            let defaults = DenoiseSettings::default();
+            let denoise_settings = DenoiseSettings {
+                iterations: args.denoise_iterations.unwrap_or(defaults.iterations),
+                strength: args.denoise_strength.unwrap_or(defaults.strength),
+            };
+            image = denoise(&film, &aovs, &denoise_settings);
+        }
+    }
+    write_output(&image, &args, &scene.output)
 }
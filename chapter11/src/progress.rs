@@ -0,0 +1,52 @@
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WIDTH: usize = 40;
+
+/// Single-line progress bar with an ETA, redrawn in place on stderr.
+pub struct ProgressBar {
+    start: Instant,
+    last_draw: Mutex<Option<Instant>>,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        ProgressBar { start: Instant::now(), last_draw: Mutex::new(None) }
+    }
+
+    pub fn update(&self, done: usize, total: usize) {
+        let now = Instant::now();
+        let mut last_draw = self.last_draw.lock().unwrap();
+        // Redrawing for every tile would flood slow terminals.
+        if done < total && matches!(*last_draw, Some(t) if now - t < Duration::from_millis(100)) {
+            return;
+        }
+        *last_draw = Some(now);
+
+        let fraction = if total == 0 { 1.0 } else { done as f64 / total as f64 };
+        let filled = (fraction * WIDTH as f64) as usize;
+        let elapsed = now - self.start;
+        let eta = if done == 0 {
+            String::from("--:--")
+        } else {
+            format_duration(elapsed.mul_f64((total - done) as f64 / done as f64))
+        };
+        let mut stderr = std::io::stderr();
+        let _ = write!(stderr, "\r[{}{}] {:3.0}% elapsed {} ETA {}",
+            "#".repeat(filled), " ".repeat(WIDTH - filled), fraction * 100.0, format_duration(elapsed), eta);
+        if done == total {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
@@ -1,136 +0,0 @@
-use std::ops;
-use std::ops::{AddAssign, SubAssign, MulAssign, DivAssign};
-#[derive(Debug, Clone, Copy)]
-pub struct Vec3 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-}
-
-impl Vec3 {
-    pub fn new(x: f32, y: f32, z: f32) -> Self {
-        Self {x, y, z}
-    }
-
-    pub fn length(&self) -> f32 {
-        self.square().sqrt()
-    }
-
-    pub fn square(&self) -> f32 {
-        self.x * self.x + self.y * self.y + self.z * self.z
-    }
-    
-    pub fn normalize(&self) -> Self {
-        let len = self.length();
-        let value = self.clone() / len;
-        return value;        
-    }
-
-    pub fn dot(&self, rhs: Vec3) -> f32 {
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
-    }
-
-    pub fn cross(&self, rhs: Vec3) -> Self {
-        let x = self.y * rhs.z - self.z*rhs.y;
-        let y = -(self.x*rhs.z - self.z*rhs.x);
-        let z = self.x*rhs.y - self.y*rhs.x;
-        Vec3::new(x,y,z)
-    }
-}
-
-impl ops::Add<Vec3> for Vec3 {
-    type Output = Vec3;
-    fn add(self, rhs: Vec3) -> Vec3 {
-        Vec3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
-    }
-}
-
-impl ops::Sub<Vec3> for Vec3 {
-    type Output = Vec3;
-    fn sub(self, rhs: Vec3) -> Vec3 {
-        Vec3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
-    }
-}
-
-impl ops::Mul<Vec3> for Vec3 {
-    type Output = Vec3;
-    fn mul(self, rhs: Vec3) -> Vec3 {
-        Vec3::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z)
-    }
-}
-
-impl ops::Mul<f32> for Vec3 {
-    type Output = Vec3;
-    fn mul(self, rhs: f32) -> Vec3 {
-        Vec3::new(self.x * rhs, self.y * rhs, self.z * rhs)
-    }
-}
-
-impl ops::Div<Vec3> for Vec3 {
-    type Output = Vec3;
-    fn div(self, rhs: Vec3) -> Vec3 {
-        Vec3::new(self.x / rhs.x, self.y / rhs.y, self.z / rhs.z)
-    }
-}
-
-impl ops::Div<f32> for Vec3 {
-    type Output = Vec3;
-    fn div(self, rhs: f32) -> Vec3 {
-        Vec3::new(self.x / rhs, self.y / rhs, self.z / rhs)
-    }
-}
-
-impl ops::Neg for Vec3 {
-    type Output = Vec3;
-    fn neg(self) -> Vec3 {
-        Vec3::new(-self.x , -self.y, -self.z)
-    }
-}
-
-impl AddAssign<Vec3> for Vec3 {
-    fn add_assign(&mut self, other: Self) {
-        self.x += other.x;
-        self.y += other.y;
-        self.z += other.z;
-    }
-}
-
-impl SubAssign<Vec3> for Vec3 {
-    fn sub_assign(&mut self, other: Self) {
-        self.x -= other.x;
-        self.y -= other.y;
-        self.z -= other.z;
-    }
-}
-
-impl MulAssign<Vec3> for Vec3 {
-    fn mul_assign(&mut self, other: Self) {
-        self.x *= other.x;
-        self.y *= other.y;
-        self.z *= other.z;
-    }
-}
-
-impl MulAssign<f32> for Vec3 {
-    fn mul_assign(&mut self, other: f32) {
-        self.x *= other;
-        self.y *= other;
-        self.z *= other;
-    }
-}
-
-impl DivAssign<Vec3> for Vec3 {
-    fn div_assign(&mut self, other: Self) {
-        self.x /= other.x;
-        self.y /= other.y;
-        self.z /= other.z;
-    }
-}
-
-impl DivAssign<f32> for Vec3 {
-    fn div_assign(&mut self, other: f32) {
-        self.x /= other;
-        self.y /= other;
-        self.z /= other;
-    }
-}
\ No newline at end of file
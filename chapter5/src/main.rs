@@ -1,50 +1,48 @@
-mod myvec;
-mod ray;
-mod hitable;
+use std::sync::Arc;
 
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::LineWriter;
+use raytracer::{save, Framebuffer, Hitable, HitableList, Lambertian, Ray, Sphere, Vec3};
 
-use ray::Ray;
-use myvec::Vec3;
-use hitable::{HitableList, Sphere, color};
+// This chapter shades by normal only, so the material is never consulted.
+fn sphere(center: Vec3, radius: f32) -> Sphere {
+    Sphere::new(center, radius, Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))))
+}
+
+fn color(r: &Ray, world: &HitableList) -> Vec3 {
+    match world.hit(r, 0.0, f32::MAX) {
+        Some(rec) => (rec.normal + Vec3::new(1.,1.,1.)) * 0.5,
+        None => {
+            let unit_direction = r.direction.normalize();
+            let t = 0.5 * (unit_direction.y + 1.0);
+            Vec3::new(1.0, 1.0, 1.0) * (1.0 - t) + Vec3::new(0.5, 0.7, 1.0) * t
+        }
+    }
+}
 
 fn main() -> std::io::Result<()>{
     let nx = 200;
     let ny = 100;
 
-    let file = File::create("test2.ppm")?;
-    let mut file = LineWriter::new(file);
-    file.write_all(b"P3\r\n")?;
-    file.write_all(format!("{} {}\r\n", nx, ny).as_bytes())?;
-    file.write_all(b"255\r\n")?;
     let lower_left_corner = Vec3::new(-2.0, -1.0, -1.0);
     let horizontal = Vec3::new(4.0, 0.0, 0.0);
     let vertical = Vec3::new(0.0, 2.0, 0.0);
     let origin = Vec3::new(0.0, 0.0, 0.0);
 
     let mut hitables = HitableList::default();
-    let sphere1 = Sphere{ center: Vec3::new(0.,0.,-1.), radius: 0.5};
-    let sphere2 = Sphere{ center: Vec3::new(0.,-100.5,-1.), radius: 100.};
+    let sphere1 = sphere(Vec3::new(0.,0.,-1.), 0.5);
+    let sphere2 = sphere(Vec3::new(0.,-100.5,-1.), 100.);
     hitables.list.push(Box::new(sphere1));
     hitables.list.push(Box::new(sphere2));    
     
+    let mut image = Framebuffer::new(nx, ny);
     for j in (0..ny).rev() {
         for i in 0..nx {
             let u = i as f32 / nx as f32;
             let v = j as f32 / ny as f32;
             let direction = lower_left_corner + horizontal * u + vertical * v;
             let r = Ray::new(origin, direction);
-            let p = r.point_at_paramter(2.0);
-            let color = color(&r, &hitables) * 255.99;
-            let ir = color.x.trunc() as u8;
-            let ig = color.y.trunc() as u8;
-            let ib = color.z.trunc() as u8;
-            file.write_all(format!("{} {} {}\r\n", ir, ig, ib).as_bytes());
+            image.set(i, ny - 1 - j, color(&r, &hitables));
         }
     }
 
-    file.flush()?;
-    Ok(())
-}
\ No newline at end of file
+    save(&image, "test2.ppm")
+}
@@ -1,64 +1,57 @@
-mod myvec;
-mod ray;
-mod hitable;
-mod camera;
+use std::sync::Arc;
 
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::LineWriter;
+use raytracer::{save, Camera, Framebuffer, Hitable, HitableList, Lambertian, Pcg32, Ray, Sampler, Sphere, Vec3};
+use raytracer::sampler::random_in_unit_sphere;
 
-use ray::Ray;
-use myvec::Vec3;
-use hitable::{HitableList, Sphere, color};
-use camera::Camera;
-use rand::Rng;
+// Every surface is a 50% grey diffuser here, so the material is never consulted.
+fn sphere(center: Vec3, radius: f32) -> Sphere {
+    Sphere::new(center, radius, Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))))
+}
 
-fn main() -> std::io::Result<()>{
+fn color(r: &Ray, world: &HitableList, sampler: &mut dyn Sampler) -> Vec3 {
+    match world.hit(r, 0.001, f32::MAX) {
+        Some(rec) => {
+            let target = rec.p + rec.normal + random_in_unit_sphere(sampler);
+            color(&Ray::new(rec.p, target - rec.p), world, sampler) * 0.5
+        }
+        None => {
+            let unit_direction = r.direction.normalize();
+            let t = 0.5 * (unit_direction.y + 1.0);
+            Vec3::new(1.0, 1.0, 1.0) * (1.0 - t) + Vec3::new(0.5, 0.7, 1.0) * t
+        }
+    }
+}
+
+fn main() -> std::io::Result<()> {
     let nx = 200;
     let ny = 100;
     let ns = 100;
 
-    let file = File::create("test2.ppm")?;
-    let mut file = LineWriter::new(file);
-    file.write_all(b"P3\r\n")?;
-    file.write_all(format!("{} {}\r\n", nx, ny).as_bytes())?;
-    file.write_all(b"255\r\n")?;
-    let lower_left_corner = Vec3::new(-2.0, -1.0, -1.0);
-    let horizontal = Vec3::new(4.0, 0.0, 0.0);
-    let vertical = Vec3::new(0.0, 2.0, 0.0);
-    let origin = Vec3::new(0.0, 0.0, 0.0);
-
     let mut hitables = HitableList::default();
-    let sphere1 = Sphere{ center: Vec3::new(0.,0.,-1.), radius: 0.5};
-    let sphere2 = Sphere{ center: Vec3::new(0.,-100.5,-1.), radius: 100.};
+    let sphere1 = sphere(Vec3::new(0.,0.,-1.), 0.5);
+    let sphere2 = sphere(Vec3::new(0.,-100.5,-1.), 100.);
     hitables.list.push(Box::new(sphere1));
     hitables.list.push(Box::new(sphere2));    
 
     let camera = Camera::default();
-    let mut rng = rand::thread_rng();
+    let mut sampler = Pcg32::new(0, 0);
+    let mut image = Framebuffer::new(nx, ny);
     
     for j in (0..ny).rev() {
         for i in 0..nx {
             let mut col = Vec3::new(0., 0., 0.);
-            for s in 0..ns {
-                let random1 = rng.gen_range(0.0..1.0) as f32;
-                let random2 = rng.gen_range(0.0..1.0) as f32;
+            for _ in 0..ns {
+                let random1 = sampler.next_f32();
+                let random2 = sampler.next_f32();
                 let u = (i as f32 + random1) / nx as f32;
                 let v = (j as f32 + random2) / ny as f32;
-                let r = camera.get_ray(u, v);
-                let p = r.point_at_paramter(2.0);
-                col += color(&r, &hitables);                
+                let r = camera.get_ray(u, v, &mut sampler);
+                col += color(&r, &hitables, &mut sampler);                
             }
             col /= ns as f32;
-            col = Vec3::new(col.x.sqrt(), col.y.sqrt(), col.z.sqrt());
-            col *= 255.99;
-            let ir = col.x.trunc() as u8;
-            let ig = col.y.trunc() as u8;
-            let ib = col.z.trunc() as u8;
-            file.write_all(format!("{} {} {}\r\n", ir, ig, ib).as_bytes());
+            image.set(i, ny - 1 - j, col);
         }
     }
 
-    file.flush()?;
-    Ok(())
+    save(&image, "test2.ppm")
 }
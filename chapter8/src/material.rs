@@ -1,57 +0,0 @@
-use crate::myvec::Vec3;
-use crate::ray::Ray;
-use crate::hitable::{HitRecord, random_in_unit_sphere};
-pub trait Material {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)>;
-}
-
-pub struct Lambertian {
-    albedo: Vec3,
-}
-
-impl Lambertian {
-    pub fn new(albedo: Vec3) -> Self {
-        Lambertian { albedo }
-    }
-}
-
-impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
-        let target = rec.p + rec.normal + random_in_unit_sphere();
-        let scattered = Ray::new(rec.p, target - rec.p);
-        let attenuation = self.albedo;
-        return Some((scattered, attenuation));
-    }
-} 
-
-
-fn reflect(v: Vec3, n: Vec3) -> Vec3 {
-    return v - n * (v.dot(n) * 2.0)
-}
-
-pub struct Metal {
-    albedo: Vec3,
-    fuzz: f32,
-}
-
-impl Metal {
-    pub fn new(albedo: Vec3, mut fuzz: f32) -> Self {
-        if fuzz >= 1.0 {
-            fuzz = 1.0;
-        }
-        Metal { albedo, fuzz }
-    }
-}
-
-impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
-        let reflected = reflect(r_in.direction.normalize(), rec.normal);
-        let scattered = Ray::new(rec.p, reflected + random_in_unit_sphere() * self.fuzz);
-        let attenuation = self.albedo;
-        if scattered.direction.dot(rec.normal) > 0.0 {
-            Some((scattered, attenuation))
-        } else {
-            None
-        }
-    }    
-}
\ No newline at end of file
@@ -0,0 +1,217 @@
+//! Arbitrary output variables: images of what the camera rays of a render
+//! hit first, for compositing and denoising.
+
+use crate::camera::Camera;
+use crate::film::Film;
+use crate::hitable::Hitable;
+use crate::image::Framebuffer;
+use crate::myvec::Vec3;
+use crate::render::{camera_sample, RenderSettings};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// One feature of the first hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// `Material::albedo`, or the background where rays miss.
+    Albedo,
+    /// World-space normal, as the object reports it.
+    Normal,
+    /// Distance from the camera along the ray.
+    Depth,
+    /// World-space hit point.
+    Position,
+    /// The material hit, numbered from 1 in the order the rows of the image
+    /// first show them, so the numbers only hold within one image.
+    MaterialId,
+    /// The `object_id` of the hit, which `Tagged` sets.
+    ObjectId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [Aov::Albedo, Aov::Normal, Aov::Depth, Aov::Position, Aov::MaterialId, Aov::ObjectId];
+
+    pub fn named(name: &str) -> Option<Aov> {
+        Aov::ALL.iter().copied().find(|aov| aov.name() == name)
+    }
+
+    /// Whether the feature can be negative, which only PFM files store;
+    /// RGBE `.hdr` files clamp it to 0.
+    pub fn is_signed(self) -> bool {
+        matches!(self, Aov::Normal | Aov::Position)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::MaterialId => "material-id",
+            Aov::ObjectId => "object-id",
+        }
+    }
+}
+
+/// Features of one pixel. `material` is the address of the material hit
+/// until the IDs are handed out.
+#[derive(Debug, Clone, Copy, Default)]
+struct Features {
+    albedo: Vec3,
+    normal: Vec3,
+    depth: f32,
+    position: Vec3,
+    material: usize,
+    object_id: u32,
+}
+
+/// The feature images of a render. Albedo, normal, depth and position are
+/// averaged over the samples drawn inside each pixel, with rays that miss
+/// counting as zero; the IDs are those of the pixel's first sample, 0
+/// where it misses.
+pub struct Aovs {
+    width: usize,
+    height: usize,
+    albedo: Vec<Vec3>,
+    normal: Vec<Vec3>,
+    depth: Vec<f32>,
+    position: Vec<Vec3>,
+    material_id: Vec<u32>,
+    object_id: Vec<u32>,
+}
+
+impl Aovs {
+    /// The feature as a float image. Depth and IDs fill all three channels.
+    pub fn image(&self, aov: Aov) -> Framebuffer {
+        let gray = |v: f32| Vec3::new(v, v, v);
+        let pixels = match aov {
+            Aov::Albedo => self.albedo.clone(),
+            Aov::Normal => self.normal.clone(),
+            Aov::Depth => self.depth.iter().map(|&d| gray(d)).collect(),
+            Aov::Position => self.position.clone(),
+            Aov::MaterialId => self.material_id.iter().map(|&id| gray(id as f32)).collect(),
+            Aov::ObjectId => self.object_id.iter().map(|&id| gray(id as f32)).collect(),
+        };
+        Framebuffer::from_pixels(self.width, self.height, pixels)
+    }
+}
+
+/// Averages the first hits of the camera rays of the samples `film` holds
+/// for pixel `(i, j)`.
+fn features(world: &dyn Hitable, camera: &Camera, settings: &RenderSettings, samples: usize, i: usize, j: usize) -> Features {
+    let mut sampler = settings.sampler.for_pixel(settings.seed, i, j, settings.max_ns());
+    let mut sum = Features::default();
+    for k in 0..samples {
+        let (_, _, r) = camera_sample(camera, settings, sampler.as_mut(), i, j, k);
+        // The integrator draws this next, so media are hit where the render hit them.
+        let r = r.with_free_path(sampler.as_mut());
+        let hit = match world.hit(&r, 0.001, f32::MAX) {
+            Some(rec) => Features {
+                albedo: rec.material.albedo(&rec),
+                normal: rec.normal,
+                depth: rec.t * r.direction.length(),
+                position: rec.p,
+                material: Arc::as_ptr(&rec.material) as *const () as usize,
+                object_id: rec.object_id,
+            },
+            None => Features { albedo: settings.integrator.background.color(&r), ..Features::default() },
+        };
+        if k == 0 {
+            sum.material = hit.material;
+            sum.object_id = hit.object_id;
+        }
+        sum.albedo += hit.albedo;
+        sum.normal += hit.normal;
+        sum.depth += hit.depth;
+        sum.position += hit.position;
+    }
+    let n = samples.max(1) as f32;
+    Features { albedo: sum.albedo / n, normal: sum.normal / n, depth: sum.depth / n, position: sum.position / n, ..sum }
+}
+
+/// Traces again the camera rays of every sample in `film`, which was
+/// rendered from `camera` with `settings`, and returns the features of
+/// their first hits. Rows are shared out among worker threads.
+pub fn render_aovs(world: &dyn Hitable, camera: &Camera, settings: &RenderSettings, film: &Film) -> Aovs {
+    let (nx, ny) = (settings.nx, settings.ny);
+    let next = AtomicUsize::new(0);
+    let rows: Mutex<Vec<Vec<Features>>> = Mutex::new(vec![Vec::new(); ny]);
+    thread::scope(|s| {
+        for _ in 0..settings.thread_count() {
+            s.spawn(|| loop {
+                let j = next.fetch_add(1, Ordering::Relaxed);
+                if j >= ny {
+                    break;
+                }
+                let row = (0..nx).map(|i| features(world, camera, settings, film.samples(i, j) as usize, i, j)).collect();
+                rows.lock().unwrap()[j] = row;
+            });
+        }
+    });
+
+    let pixels: Vec<Features> = rows.into_inner().unwrap().into_iter().flatten().collect();
+    let mut ids = HashMap::new();
+    ids.insert(0, 0);
+    let material_id = pixels.iter().map(|p| {
+        let next = ids.len() as u32;
+        *ids.entry(p.material).or_insert(next)
+    }).collect();
+    Aovs {
+        width: nx,
+        height: ny,
+        albedo: pixels.iter().map(|p| p.albedo).collect(),
+        normal: pixels.iter().map(|p| p.normal).collect(),
+        depth: pixels.iter().map(|p| p.depth).collect(),
+        position: pixels.iter().map(|p| p.position).collect(),
+        material_id,
+        object_id: pixels.iter().map(|p| p.object_id).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::HitableList;
+    use crate::image::ImageFormat;
+    use crate::render::render_film;
+    use crate::testing::spheres;
+    use crate::tonemap::OutputSettings;
+
+    #[test]
+    fn features_come_from_the_first_hit() {
+        let world = spheres();
+        let camera = Camera::default();
+        let settings = RenderSettings { nx: 40, ny: 20, ns: 2, ..RenderSettings::default() };
+        let film = render_film(&world, &HitableList::default(), &camera, &settings, |_, _| {});
+        let aovs = render_aovs(&world, &camera, &settings, &film);
+
+        // Straight ahead is the front of the small sphere, which the rows
+        // show before the ground.
+        let (i, j) = (20, 10);
+        assert!((aovs.image(Aov::Depth).get(i, j).x - 0.5).abs() < 0.02);
+        assert!(aovs.image(Aov::Normal).get(i, j).z > 0.99);
+        assert!((aovs.image(Aov::Position).get(i, j).z + 0.5).abs() < 0.02);
+        assert!((aovs.image(Aov::Albedo).get(i, j) - Vec3::new(0.1, 0.2, 0.5)).length() < 1e-6);
+        assert_eq!((aovs.image(Aov::ObjectId).get(i, j).x, aovs.image(Aov::MaterialId).get(i, j).x), (2.0, 1.0));
+        // Material ids count up in scan order, which meets the metal
+        // sphere before the ground.
+        assert_eq!((aovs.image(Aov::ObjectId).get(i, 19).x, aovs.image(Aov::MaterialId).get(i, 19).x), (1.0, 3.0));
+
+        // Normals facing left survive a float image.
+        let mut pfm = Vec::new();
+        ImageFormat::Pfm.writer(OutputSettings::default()).write(&aovs.image(Aov::Normal), &mut pfm).unwrap();
+        let header = b"PF\n40 20\n-1.0\n".len();
+        // Rows are stored bottom up.
+        let at = |i: usize, j: usize| header + ((19 - j) * 40 + i) * 12;
+        let x = |offset: usize| f32::from_le_bytes([pfm[offset], pfm[offset + 1], pfm[offset + 2], pfm[offset + 3]]);
+        assert!(x(at(16, 10)) < -0.2, "{}", x(at(16, 10)));
+        assert_eq!(x(at(16, 10)), aovs.image(Aov::Normal).get(16, 10).x);
+
+        // The sky has no features but its color.
+        assert_eq!(aovs.image(Aov::Depth).get(i, 0).x, 0.0);
+        assert_eq!(aovs.image(Aov::MaterialId).get(i, 0).x, 0.0);
+        assert!(aovs.image(Aov::Albedo).get(i, 0).z > 0.99);
+    }
+}
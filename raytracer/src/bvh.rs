@@ -0,0 +1,215 @@
+use crate::aabb::Aabb;
+use crate::hitable::{HitRecord, Hitable, HitableList};
+use crate::ray::Ray;
+
+/// Bounding volume hierarchy over the objects of a `HitableList`.
+///
+/// Inner nodes are split with the surface area heuristic, so a ray only
+/// visits the objects whose boxes it actually passes through. Objects
+/// without a bounding box are kept beside the tree and tested by every ray.
+pub struct BvhNode {
+    bbox: Aabb,
+    left: Box<dyn Hitable>,
+    right: Box<dyn Hitable>,
+    /// Only ever filled at the root.
+    unbounded: HitableList,
+}
+
+struct Primitive {
+    bbox: Aabb,
+    hitable: Box<dyn Hitable>,
+}
+
+impl BvhNode {
+    /// Builds the hierarchy for rays with times in `[time0, time1]`, usually
+    /// the camera's shutter interval.
+    pub fn new(list: HitableList, time0: f32, time1: f32) -> Self {
+        let mut primitives = Vec::new();
+        let mut unbounded = HitableList::default();
+        for hitable in list.list {
+            match hitable.bounding_box(time0, time1) {
+                Some(bbox) => primitives.push(Primitive { bbox, hitable }),
+                None => unbounded.add(hitable),
+            }
+        }
+
+        let node = match primitives.len() {
+            0 => BvhNode {
+                bbox: Aabb::empty(),
+                left: Box::new(HitableList::default()),
+                right: Box::new(HitableList::default()),
+                unbounded: HitableList::default(),
+            },
+            1 => {
+                let only = primitives.pop().unwrap();
+                BvhNode {
+                    bbox: only.bbox,
+                    left: only.hitable,
+                    right: Box::new(HitableList::default()),
+                    unbounded: HitableList::default(),
+                }
+            }
+            _ => build_node(primitives),
+        };
+        BvhNode { unbounded, ..node }
+    }
+}
+
+fn build(mut primitives: Vec<Primitive>) -> Box<dyn Hitable> {
+    if primitives.len() == 1 {
+        return primitives.pop().unwrap().hitable;
+    }
+    Box::new(build_node(primitives))
+}
+
+fn build_node(mut primitives: Vec<Primitive>) -> BvhNode {
+    let (axis, split) = sah_split(&mut primitives);
+    sort_by_axis(&mut primitives, axis);
+    let right = primitives.split_off(split);
+    let bbox = bounds(&primitives).union(&bounds(&right));
+    BvhNode { bbox, left: build(primitives), right: build(right), unbounded: HitableList::default() }
+}
+
+fn bounds(primitives: &[Primitive]) -> Aabb {
+    primitives.iter().fold(Aabb::empty(), |acc, p| acc.union(&p.bbox))
+}
+
+fn sort_by_axis(primitives: &mut [Primitive], axis: usize) {
+    primitives.sort_by(|a, b| {
+        a.bbox.centroid()[axis].partial_cmp(&b.bbox.centroid()[axis]).unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// Picks the axis and split index that minimise the surface area heuristic
+/// `area(left) * n_left + area(right) * n_right` over centroid-sorted objects.
+fn sah_split(primitives: &mut [Primitive]) -> (usize, usize) {
+    let n = primitives.len();
+    let mut best = (0, n / 2);
+    let mut best_cost = f32::MAX;
+    let mut right_areas = vec![0.0; n];
+    for axis in 0..3 {
+        sort_by_axis(primitives, axis);
+
+        let mut acc = Aabb::empty();
+        for i in (1..n).rev() {
+            acc = acc.union(&primitives[i].bbox);
+            right_areas[i] = acc.surface_area();
+        }
+
+        let mut acc = Aabb::empty();
+        for i in 1..n {
+            acc = acc.union(&primitives[i - 1].bbox);
+            let cost = acc.surface_area() * i as f32 + right_areas[i] * (n - i) as f32;
+            if cost < best_cost {
+                best_cost = cost;
+                best = (axis, i);
+            }
+        }
+    }
+    best
+}
+
+impl Hitable for BvhNode {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let bounded = if self.bbox.hit(r, t_min, t_max) {
+            let left = self.left.hit(r, t_min, t_max);
+            let closest = match &left {
+                Some(rec) => rec.t,
+                None => t_max,
+            };
+            match self.right.hit(r, t_min, closest) {
+                Some(rec) => Some(rec),
+                None => left,
+            }
+        } else {
+            None
+        };
+        if self.unbounded.length() == 0 {
+            return bounded;
+        }
+        let closest = bounded.as_ref().map_or(t_max, |rec| rec.t);
+        match self.unbounded.hit(r, t_min, closest) {
+            Some(rec) => Some(rec),
+            None => bounded,
+        }
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        if self.unbounded.length() > 0 {
+            return None;
+        }
+        Some(self.bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::random_scene;
+    use crate::material::{Lambertian, Material};
+    use crate::myvec::Vec3;
+    use crate::sampler::{Pcg32, Sampler};
+    use std::sync::Arc;
+
+    /// The plane `y = 0.3`, which has no bounding box.
+    struct Floor(Arc<dyn Material>);
+
+    impl Hitable for Floor {
+        fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+            let t = (0.3 - r.origin.y) / r.direction.y;
+            if !(t > t_min && t < t_max) {
+                return None;
+            }
+            let (p, normal) = (r.point_at_paramter(t), Vec3::new(0., 1., 0.));
+            Some(HitRecord { t, p, normal, u: 0.0, v: 0.0, material: Arc::clone(&self.0), object_id: 0 })
+        }
+
+        fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+            None
+        }
+    }
+
+    fn with_floor(mut list: HitableList) -> HitableList {
+        list.add(Box::new(Floor(Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))))));
+        list
+    }
+
+    fn assert_same_hits(list: &HitableList, bvh: &BvhNode) {
+        let mut rng = Pcg32::new(1, 0);
+        for _ in 0..2000 {
+            let origin = Vec3::new(rng.next_range(-15., 15.), rng.next_range(0.1, 5.), rng.next_range(-15., 15.));
+            let target = Vec3::new(rng.next_range(-11., 11.), rng.next_range(-0.5, 1.5), rng.next_range(-11., 11.));
+            let r = Ray::new(origin, target - origin);
+            let expected = list.hit(&r, 0.001, f32::MAX);
+            let actual = bvh.hit(&r, 0.001, f32::MAX);
+            match (expected, actual) {
+                (None, None) => {}
+                (Some(a), Some(b)) => {
+                    assert_eq!(a.t.to_bits(), b.t.to_bits());
+                    assert_eq!((a.p.x, a.p.y, a.p.z), (b.p.x, b.p.y, b.p.z));
+                }
+                (a, b) => panic!("linear hit: {}, bvh hit: {}", a.is_some(), b.is_some()),
+            }
+        }
+    }
+
+    #[test]
+    fn bvh_matches_linear_list() {
+        let list = random_scene(&mut Pcg32::new(42, 0));
+        let bvh = BvhNode::new(random_scene(&mut Pcg32::new(42, 0)), 0.0, 0.0);
+        assert_same_hits(&list, &bvh);
+    }
+
+    #[test]
+    fn unbounded_objects_sit_beside_the_tree() {
+        let list = with_floor(random_scene(&mut Pcg32::new(42, 0)));
+        let bvh = BvhNode::new(with_floor(random_scene(&mut Pcg32::new(42, 0))), 0.0, 0.0);
+        assert!(bvh.bounding_box(0.0, 0.0).is_none());
+        assert_same_hits(&list, &bvh);
+
+        // The tree still works when nothing in it has a box.
+        let only = BvhNode::new(with_floor(HitableList::default()), 0.0, 0.0);
+        let r = Ray::new(Vec3::new(0., 1., 0.), Vec3::new(0., -1., 0.));
+        assert!((only.hit(&r, 0.001, f32::MAX).unwrap().t - 0.7).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::myvec::Vec3;
+use crate::ray::Ray;
+use crate::sampler::{sample_unit_disk, Sampler};
+
+/// The parameters `Camera::new` takes, minus the aspect ratio, which
+/// comes from the image size. Scene files and command lines fill this in.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraSettings {
+    pub lookfrom: Vec3,
+    pub lookat: Vec3,
+    pub vup: Vec3,
+    pub vfov: f32,
+    pub aperture: f32,
+    pub focus_dist: f32,
+    /// Interval over which ray times are spread; equal values give a still
+    /// image.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+}
+
+impl CameraSettings {
+    pub fn build(&self, aspect: f32) -> Camera {
+        Camera::new(self.lookfrom, self.lookat, self.vup, self.vfov, aspect, self.aperture, self.focus_dist)
+            .with_shutter(self.shutter_open, self.shutter_close)
+    }
+}
+
+pub struct Camera {
+    origin: Vec3,
+    lower_left_corner: Vec3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    lens_radius: f32,
+    u: Vec3,
+    v: Vec3,
+    shutter_open: f32,
+    shutter_close: f32,
+}
+
+/// The fixed pinhole camera of chapters 6 to 9: looking down -z from the
+/// origin at a 4x2 image plane.
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::new(0., 0., 0.),
+            lower_left_corner: Vec3::new(-2., -1., -1.),
+            horizontal: Vec3::new(4., 0., 0.),
+            vertical: Vec3::new(0., 2., 0.),
+            lens_radius: 0.0,
+            u: Vec3::new(1., 0., 0.),
+            v: Vec3::new(0., 1., 0.),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, vfov: f32, aspect: f32, aperture: f32, focus_dist: f32) -> Self {
+        let lens_radius = aperture * 0.5;
+        let theta = vfov * std::f32::consts::PI/ 180.0;
+        let half_height = (theta * 0.5).tan();
+        let half_width = aspect * half_height;
+        let origin = lookfrom;
+        let w = (lookfrom - lookat).normalize();
+        let u = vup.cross(w).normalize();
+        let v = w.cross(u);
+
+        let lower_left_corner = origin - u* half_width*focus_dist - v * half_height*focus_dist - w * focus_dist;
+        let horizontal = u * (half_width * 2.0 * focus_dist);
+        let vertical = v * (half_height * 2.0 * focus_dist);
+
+        Self {
+            origin, lower_left_corner, horizontal, vertical, lens_radius, u, v,
+            shutter_open: 0.0, shutter_close: 0.0,
+        }
+    }
+
+    /// Spreads ray times uniformly over `[open, close]`.
+    pub fn with_shutter(mut self, open: f32, close: f32) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    pub fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        let rd = sample_unit_disk(sampler) * self.lens_radius;
+        let offset = self.u * rd.x + self.v * rd.y;
+        let direction = self.lower_left_corner + self.horizontal*s + self.vertical*t - self.origin - offset;
+        // A still camera draws no time sample, so its images stay the same.
+        let time = if self.shutter_close > self.shutter_open {
+            sampler.next_range(self.shutter_open, self.shutter_close)
+        } else {
+            self.shutter_open
+        };
+        Ray::with_time(self.origin + offset, direction, time)
+    }
+}
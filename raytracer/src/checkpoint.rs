@@ -0,0 +1,182 @@
+//! Checkpoints of progressive renders: the film accumulated over the
+//! passes so far, so that a stopped render can go on where it left off.
+
+use crate::camera::CameraSettings;
+use crate::film::Film;
+use crate::render::RenderSettings;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"RTCHECK1";
+
+/// A progressive render after `passes` passes.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub passes: usize,
+    pub film: Film,
+}
+
+/// What a checkpoint only fits unchanged: the render settings, the camera
+/// and the scene, given as its source (the scene file's bytes, or the name
+/// of a built-in scene). Files the scene refers to, such as meshes, are not
+/// covered.
+pub fn fingerprint(settings: &RenderSettings, camera: &CameraSettings, scene: &[u8]) -> String {
+    // FNV-1a, which unlike the standard hasher stays the same across builds.
+    let hash = scene.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3));
+    format!("{}x{} ns {} seed {} {:?} {:?} {:?} {:?} {:?} scene {:016x}", settings.nx, settings.ny, settings.ns,
+            settings.seed, settings.sampler, settings.filter, settings.adaptive, settings.integrator, camera, hash)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint of a render with the given `fingerprint`.
+    pub fn write(&self, out: &mut dyn Write, fingerprint: &str) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&(fingerprint.len() as u64).to_le_bytes())?;
+        out.write_all(fingerprint.as_bytes())?;
+        out.write_all(&(self.passes as u64).to_le_bytes())?;
+        self.film.write(out)
+    }
+
+    /// Reads a checkpoint back, failing if it was made with another
+    /// `fingerprint`.
+    pub fn read(input: &mut dyn Read, fingerprint: &str) -> io::Result<Checkpoint> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a render checkpoint"));
+        }
+        let mut len = [0; 8];
+        input.read_exact(&mut len)?;
+        let mut written = Vec::new();
+        input.take(u64::from_le_bytes(len)).read_to_end(&mut written)?;
+        if written != fingerprint.as_bytes() {
+            return Err(invalid("checkpoint was made with another scene, camera or render settings"));
+        }
+        let mut passes = [0; 8];
+        input.read_exact(&mut passes)?;
+        let film = Film::read(input)?;
+        Ok(Checkpoint { passes: u64::from_le_bytes(passes) as usize, film })
+    }
+
+    /// Writes the checkpoint to `path`. The file is replaced only once the
+    /// new one is complete, so being stopped while saving never loses the
+    /// previous checkpoint.
+    pub fn save(&self, path: impl AsRef<Path>, fingerprint: &str) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut out = BufWriter::new(File::create(&partial)?);
+        self.write(&mut out, fingerprint)?;
+        out.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+        fs::rename(&partial, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>, fingerprint: &str) -> io::Result<Checkpoint> {
+        Checkpoint::read(&mut BufReader::new(File::open(path)?), fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::Filter;
+    use crate::hitable::HitableList;
+    use crate::myvec::Vec3;
+    use crate::render::{render_film, render_pass, AdaptiveSettings};
+    use crate::testing::{assert_same_film, spheres};
+
+    fn settings() -> RenderSettings {
+        RenderSettings {
+            nx: 24,
+            ny: 12,
+            ns: 2,
+            adaptive: Some(AdaptiveSettings { threshold: 0.05, max_ns: 16 }),
+            filter: Filter::named("gaussian").unwrap(),
+            seed: 5,
+            tile_size: 5,
+            ..RenderSettings::default()
+        }
+    }
+
+    fn camera() -> CameraSettings {
+        CameraSettings {
+            lookfrom: Vec3::default(),
+            lookat: Vec3::new(0., 0., -1.),
+            vup: Vec3::new(0., 1., 0.),
+            vfov: 90.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    fn key(settings: &RenderSettings) -> String {
+        fingerprint(settings, &camera(), b"three spheres")
+    }
+
+    #[test]
+    fn resumed_render_matches_an_uninterrupted_one() {
+        let (world, lights, camera) = (spheres(), HitableList::default(), Camera::default());
+        let settings = settings();
+        let mut whole = Film::new(settings.nx, settings.ny, settings.filter);
+        for _ in 0..4 {
+            render_pass(&world, &lights, &camera, &settings, &mut whole, |_, _| {});
+        }
+
+        let mut film = Film::new(settings.nx, settings.ny, settings.filter);
+        render_pass(&world, &lights, &camera, &settings, &mut film, |_, _| {});
+        let mut bytes = Vec::new();
+        Checkpoint { passes: 1, film }.write(&mut bytes, &key(&settings)).unwrap();
+        let resumed = RenderSettings { threads: 3, ..settings };
+        let mut checkpoint = Checkpoint::read(&mut bytes.as_slice(), &key(&resumed)).unwrap();
+        assert_eq!(checkpoint.passes, 1);
+        for _ in 1..4 {
+            render_pass(&world, &lights, &camera, &resumed, &mut checkpoint.film, |_, _| {});
+        }
+
+        assert_same_film(&whole, &checkpoint.film);
+    }
+
+    #[test]
+    fn checkpointed_adaptive_render_matches_render_film() {
+        let (world, lights, camera) = (spheres(), HitableList::default(), Camera::default());
+        let settings = settings();
+        let whole = render_film(&world, &lights, &camera, &settings, |_, _| {});
+
+        let mut film = Film::new(settings.nx, settings.ny, settings.filter);
+        for _ in 0..2 {
+            render_pass(&world, &lights, &camera, &settings, &mut film, |_, _| {});
+        }
+        let mut bytes = Vec::new();
+        Checkpoint { passes: 2, film }.write(&mut bytes, &key(&settings)).unwrap();
+        let mut checkpoint = Checkpoint::read(&mut bytes.as_slice(), &key(&settings)).unwrap();
+        while render_pass(&world, &lights, &camera, &settings, &mut checkpoint.film, |_, _| {}) > 0 {}
+
+        assert_same_film(&whole, &checkpoint.film);
+        let counts: Vec<u32> = (0..settings.nx).map(|i| whole.samples(i, 8)).collect();
+        assert!(counts.iter().any(|&n| n > 2) && counts.iter().any(|&n| n < 16), "{:?}", counts);
+    }
+
+    #[test]
+    fn checkpoints_only_fit_their_scene_camera_and_settings() {
+        let settings = settings();
+        let checkpoint = Checkpoint { passes: 2, film: Film::new(settings.nx, settings.ny, settings.filter) };
+        let mut bytes = Vec::new();
+        checkpoint.write(&mut bytes, &key(&settings)).unwrap();
+        assert!(Checkpoint::read(&mut bytes.as_slice(), &key(&settings)).is_ok());
+        let other = RenderSettings { seed: 6, ..settings };
+        assert!(Checkpoint::read(&mut bytes.as_slice(), &key(&other)).is_err());
+        let moved = CameraSettings { lookfrom: Vec3::new(0., 0.5, 0.), ..camera() };
+        assert!(Checkpoint::read(&mut bytes.as_slice(), &fingerprint(&settings, &moved, b"three spheres")).is_err());
+        assert!(Checkpoint::read(&mut bytes.as_slice(), &fingerprint(&settings, &camera(), b"cornell")).is_err());
+        assert!(Checkpoint::read(&mut &bytes[..bytes.len() - 1], &key(&settings)).is_err());
+        assert!(Checkpoint::read(&mut &b"P3\n"[..], &key(&settings)).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! Denoising of the linear film, before exposure and tone mapping, with
+//! the edge-avoiding à-trous wavelet filter of Dammertz et al. (2010).
+//! Neighbours are blended in unless the feature images show an edge
+//! between them, or their brightness differs by more than the noise the
+//! film measured can explain, as in SVGF (Schied et al. 2017).
+
+use crate::aov::{Aov, Aovs};
+use crate::film::{luminance, Film};
+use crate::image::Framebuffer;
+use crate::myvec::Vec3;
+
+/// B3-spline weights of the 5x5 kernel, along one axis.
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+/// Power of the cosine between two normals.
+const NORMAL_POWER: i32 = 128;
+/// Albedo difference at which the weight falls to `1 / e`.
+const ALBEDO_SIGMA: f32 = 0.1;
+/// Depth difference, relative to the depth, per pixel of distance at
+/// which the weight falls to `1 / e`.
+const DEPTH_SIGMA: f32 = 0.05;
+/// Luminance difference, in standard errors, at which the weight falls to
+/// `1 / e` at strength 1.
+const LUMINANCE_SIGMA: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DenoiseSettings {
+    /// Filter passes. Pass `k` spreads the kernel over pixels `2^k` apart,
+    /// so five passes reach 62 pixels in each direction.
+    pub iterations: usize,
+    /// Scales how much brighter or darker than a pixel its neighbours may
+    /// be and still be blended in; higher values smooth more. Must be
+    /// positive.
+    pub strength: f32,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        DenoiseSettings { iterations: 5, strength: 1.0 }
+    }
+}
+
+/// Guide images for the edge-stopping weights.
+struct Features {
+    width: usize,
+    albedo: Vec<Vec3>,
+    normal: Vec<Vec3>,
+    depth: Vec<f32>,
+}
+
+impl Features {
+    /// How much pixel `q`, `distance` pixels away, looks like it shows the
+    /// same surface as pixel `p`.
+    fn similarity(&self, p: usize, q: usize, distance: f32) -> f32 {
+        let (np, nq) = (self.normal[p], self.normal[q]);
+        let normal = match (np.length() > 0.0, nq.length() > 0.0) {
+            (true, true) => np.dot(nq).max(0.0).powi(NORMAL_POWER),
+            (false, false) => 1.0,
+            _ => 0.0,
+        };
+        let albedo = (self.albedo[p] - self.albedo[q]).length() / ALBEDO_SIGMA;
+        let depth = (self.depth[p] - self.depth[q]).abs() / (DEPTH_SIGMA * self.depth[p] * distance + 1e-6);
+        normal * (-albedo * albedo - depth).exp()
+    }
+}
+
+/// One à-trous pass with taps `step` pixels apart over `color`, whose
+/// per-pixel luminance variance is `variance`. Returns the filtered color
+/// and its variance.
+fn filter_pass(color: &[Vec3], variance: &[f32], features: &Features, step: usize, strength: f32) -> (Vec<Vec3>, Vec<f32>) {
+    let width = features.width;
+    let height = color.len() / width.max(1);
+    let mut filtered = Vec::with_capacity(color.len());
+    let mut filtered_variance = Vec::with_capacity(color.len());
+    for j in 0..height {
+        for i in 0..width {
+            let p = j * width + i;
+            let lp = luminance(color[p]);
+            let sigma = LUMINANCE_SIGMA * strength * variance[p].sqrt() + 1e-6;
+            let (mut sum, mut total, mut sum_variance) = (Vec3::default(), 0.0, 0.0);
+            for (dy, ky) in KERNEL.iter().enumerate() {
+                let y = j as i64 + (dy as i64 - 2) * step as i64;
+                if y < 0 || y >= height as i64 {
+                    continue;
+                }
+                for (dx, kx) in KERNEL.iter().enumerate() {
+                    let x = i as i64 + (dx as i64 - 2) * step as i64;
+                    if x < 0 || x >= width as i64 {
+                        continue;
+                    }
+                    let q = y as usize * width + x as usize;
+                    let distance = step as f32 * (((dx as i64 - 2).pow(2) + (dy as i64 - 2).pow(2)) as f32).sqrt();
+                    let brightness = (lp - luminance(color[q])).abs() / sigma;
+                    let w = kx * ky * features.similarity(p, q, distance) * (-brightness).exp();
+                    // Also skips the NaNs of broken samples.
+                    if w > 0.0 {
+                        sum += color[q] * w;
+                        total += w;
+                        sum_variance += w * w * variance[q];
+                    }
+                }
+            }
+            if total > 0.0 {
+                filtered.push(sum / total);
+                filtered_variance.push(sum_variance / (total * total));
+            } else {
+                filtered.push(color[p]);
+                filtered_variance.push(variance[p]);
+            }
+        }
+    }
+    (filtered, filtered_variance)
+}
+
+/// `variance` blurred with a 3x3 Gaussian, since a few samples say little
+/// about the noise of one pixel.
+fn blur_variance(variance: &[f32], width: usize) -> Vec<f32> {
+    const WEIGHTS: [f32; 3] = [0.25, 0.5, 0.25];
+    let height = variance.len() / width.max(1);
+    let mut blurred = Vec::with_capacity(variance.len());
+    for j in 0..height {
+        for i in 0..width {
+            let (mut sum, mut total) = (0.0, 0.0);
+            for (dy, wy) in WEIGHTS.iter().enumerate() {
+                for (dx, wx) in WEIGHTS.iter().enumerate() {
+                    let (x, y) = ((i + dx).wrapping_sub(1), (j + dy).wrapping_sub(1));
+                    if x < width && y < height {
+                        sum += wx * wy * variance[y * width + x];
+                        total += wx * wy;
+                    }
+                }
+            }
+            blurred.push(sum / total);
+        }
+    }
+    blurred
+}
+
+/// Smooths the image of `film`, guided by the albedo, normal and depth of
+/// `aovs`, which must come from the same render. Pixels with less than
+/// two samples have no noise estimate and are blended freely.
+pub fn denoise(film: &Film, aovs: &Aovs, settings: &DenoiseSettings) -> Framebuffer {
+    let image = film.to_framebuffer();
+    let (width, height) = (image.width(), image.height());
+    let features = Features {
+        width,
+        albedo: aovs.image(Aov::Albedo).pixels().to_vec(),
+        normal: aovs.image(Aov::Normal).pixels().iter()
+            .map(|&n| if n.length() > 0.0 { n.normalize() } else { n })
+            .collect(),
+        depth: aovs.image(Aov::Depth).pixels().iter().map(|d| d.x).collect(),
+    };
+    let mut color = image.pixels().to_vec();
+    let variance: Vec<f32> = (0..height)
+        .flat_map(|j| (0..width).map(move |i| film.standard_error(i, j).powi(2)))
+        .collect();
+    let mut variance = blur_variance(&variance, width);
+    for k in 0..settings.iterations {
+        let step = 1 << k;
+        if step >= width.max(height) {
+            // Every tap but the centre falls outside the image from here on.
+            break;
+        }
+        let (c, v) = filter_pass(&color, &variance, &features, step, settings.strength);
+        color = c;
+        variance = v;
+    }
+    Framebuffer::from_pixels(width, height, color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aov::render_aovs;
+    use crate::camera::Camera;
+    use crate::hitable::{HitableList, Sphere};
+    use crate::integrator::{Background, Integrator};
+    use crate::material::Lambertian;
+    use crate::render::{render_film, RenderSettings};
+    use crate::testing::spheres;
+    use std::sync::Arc;
+
+    fn mean_squared_error(a: &Framebuffer, b: &Framebuffer) -> f32 {
+        let sum: f32 = a.pixels().iter().zip(b.pixels()).map(|(p, q)| (*p - *q).dot(*p - *q)).sum();
+        sum / a.pixels().len() as f32
+    }
+
+    #[test]
+    fn denoising_brings_a_noisy_render_closer_to_a_converged_one() {
+        let (world, lights, camera) = (spheres(), HitableList::default(), Camera::default());
+        let settings = RenderSettings { nx: 60, ny: 30, ns: 4, seed: 1, ..RenderSettings::default() };
+        let reference = RenderSettings { ns: 256, seed: 2, ..settings };
+        let truth = render_film(&world, &lights, &camera, &reference, |_, _| {}).to_framebuffer();
+
+        let film = render_film(&world, &lights, &camera, &settings, |_, _| {});
+        let aovs = render_aovs(&world, &camera, &settings, &film);
+        let noisy = mean_squared_error(&film.to_framebuffer(), &truth);
+        let denoised = mean_squared_error(&denoise(&film, &aovs, &DenoiseSettings::default()), &truth);
+        assert!(denoised < 0.5 * noisy, "{} -> {}", noisy, denoised);
+    }
+
+    #[test]
+    fn pixels_without_noise_keep_their_color() {
+        let mut world = HitableList::default();
+        world.add(Box::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5,
+            Arc::new(Lambertian::new(Vec3::new(0.1, 0.2, 0.5))))));
+        let world = world.tagged();
+        let (lights, camera) = (HitableList::default(), Camera::default());
+        let integrator = Integrator { background: Background::Solid(Vec3::new(1., 1., 1.)), ..Integrator::default() };
+        let settings = RenderSettings { nx: 40, ny: 20, ns: 4, integrator, ..RenderSettings::default() };
+        let film = render_film(&world, &lights, &camera, &settings, |_, _| {});
+        let aovs = render_aovs(&world, &camera, &settings, &film);
+        let denoised = denoise(&film, &aovs, &DenoiseSettings::default());
+        // The background next to the noisy sphere included.
+        let mut clean = 0;
+        for j in 0..20 {
+            for i in 0..40 {
+                if film.standard_error(i, j) == 0.0 {
+                    assert!((denoised.get(i, j) - film.to_framebuffer().get(i, j)).length() < 1e-6);
+                    clean += 1;
+                }
+            }
+        }
+        assert!(clean > 0 && clean < 40 * 20);
+    }
+}
@@ -0,0 +1,484 @@
+//! Pixel reconstruction. Samples are weighted by a filter centred on each
+//! nearby pixel and the image is the weighted average, so a sample may
+//! count towards several pixels.
+
+use crate::image::Framebuffer;
+use crate::myvec::Vec3;
+use std::f32::consts::PI;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+/// Separable reconstruction filter. `radius` is in pixels; each filter
+/// is zero at and beyond it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Equal weight for every sample within `radius`. With the default
+    /// radius of 0.5 every sample lands in exactly one pixel, as with plain
+    /// averaging.
+    Box { radius: f32 },
+    /// Weight falling linearly to zero at `radius`.
+    Tent { radius: f32 },
+    /// Gaussian of falloff `alpha`, shifted down to reach zero at `radius`.
+    Gaussian { radius: f32, alpha: f32 },
+    /// The Mitchell-Netravali cubic, stretched over `radius`. `b = c = 1/3`
+    /// is the pair its authors recommend.
+    Mitchell { radius: f32, b: f32, c: f32 },
+    /// Windowed sinc with as many lobes as `radius`.
+    Lanczos { radius: f32 },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter {
+    /// Tag and parameters, for storing the filter in a checkpoint.
+    fn encode(&self) -> (u8, [f32; 3]) {
+        match *self {
+            Filter::Box { radius } => (0, [radius, 0.0, 0.0]),
+            Filter::Tent { radius } => (1, [radius, 0.0, 0.0]),
+            Filter::Gaussian { radius, alpha } => (2, [radius, alpha, 0.0]),
+            Filter::Mitchell { radius, b, c } => (3, [radius, b, c]),
+            Filter::Lanczos { radius } => (4, [radius, 0.0, 0.0]),
+        }
+    }
+
+    fn decode(tag: u8, [radius, p, q]: [f32; 3]) -> Option<Filter> {
+        Some(match tag {
+            0 => Filter::Box { radius },
+            1 => Filter::Tent { radius },
+            2 => Filter::Gaussian { radius, alpha: p },
+            3 => Filter::Mitchell { radius, b: p, c: q },
+            4 => Filter::Lanczos { radius },
+            _ => return None,
+        })
+    }
+
+    /// The named filter with its usual radius and parameters: `box`, `tent`,
+    /// `gaussian`, `mitchell` or `lanczos`.
+    pub fn named(name: &str) -> Option<Filter> {
+        match name {
+            "box" => Some(Filter::Box { radius: 0.5 }),
+            "tent" => Some(Filter::Tent { radius: 1.0 }),
+            "gaussian" => Some(Filter::Gaussian { radius: 1.5, alpha: 2.0 }),
+            "mitchell" => Some(Filter::Mitchell { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 }),
+            "lanczos" => Some(Filter::Lanczos { radius: 3.0 }),
+            _ => None,
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius } => radius,
+        }
+    }
+
+    /// The same filter stretched to `radius`, or `None` unless `radius` is
+    /// finite and positive.
+    pub fn with_radius(self, radius: f32) -> Option<Filter> {
+        if !(radius.is_finite() && radius > 0.0) {
+            return None;
+        }
+        Some(match self {
+            Filter::Box { .. } => Filter::Box { radius },
+            Filter::Tent { .. } => Filter::Tent { radius },
+            Filter::Gaussian { alpha, .. } => Filter::Gaussian { radius, alpha },
+            Filter::Mitchell { b, c, .. } => Filter::Mitchell { radius, b, c },
+            Filter::Lanczos { .. } => Filter::Lanczos { radius },
+        })
+    }
+
+    /// Unnormalized weight along one axis of a sample `d` pixels from a
+    /// pixel centre. The weights are divided out per pixel, so only their
+    /// ratios matter; Mitchell and Lanczos weights can be negative.
+    pub fn evaluate(&self, d: f32) -> f32 {
+        match *self {
+            // Half-open, so that a sample on the border between two pixels
+            // counts towards only one of them.
+            Filter::Box { radius } => if d >= -radius && d < radius { 1.0 } else { 0.0 },
+            Filter::Tent { radius } => (radius - d.abs()).max(0.0),
+            Filter::Gaussian { radius, alpha } => ((-alpha * d * d).exp() - (-alpha * radius * radius).exp()).max(0.0),
+            Filter::Mitchell { radius, b, c } => {
+                let x = 2.0 * d.abs() / radius;
+                if x >= 2.0 {
+                    0.0
+                } else if x >= 1.0 {
+                    ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b)) / 6.0
+                }
+            }
+            Filter::Lanczos { radius } => if d.abs() < radius { sinc(d) * sinc(d / radius) } else { 0.0 },
+        }
+    }
+}
+
+/// Running count, mean and sum of squared deviations of the luminance of
+/// the samples drawn inside one pixel, after Welford.
+#[derive(Debug, Clone, Copy, Default)]
+struct PixelStats {
+    count: u32,
+    mean: f32,
+    m2: f32,
+}
+
+impl PixelStats {
+    fn add(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Statistics of both sets of samples together (Chan et al.).
+    fn combine(&mut self, other: &PixelStats) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let ratio = other.count as f32 / count as f32;
+        self.m2 += other.m2 + delta * delta * self.count as f32 * ratio;
+        self.mean += delta * ratio;
+        self.count = count;
+    }
+}
+
+pub(crate) fn luminance(c: Vec3) -> f32 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+fn read_u64(input: &mut dyn Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(input: &mut dyn Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Float accumulation buffer for a `width` by `height` image, or for a
+/// rectangle of one. Sample positions are in raster coordinates: `x` to
+/// the right and `y` down from the top-left corner, with pixel `(i, j)`
+/// covering `[i, i + 1) x [j, j + 1)`.
+///
+/// Besides the filtered sums, the film counts the samples drawn inside
+/// each pixel and tracks their variance, which says how converged the
+/// pixel is.
+#[derive(Debug, Clone)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    filter: Filter,
+    /// The part of the image this film covers.
+    x: Range<usize>,
+    y: Range<usize>,
+    sums: Vec<Vec3>,
+    weights: Vec<f32>,
+    stats: Vec<PixelStats>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Film {
+            width,
+            height,
+            filter,
+            x: 0..width,
+            y: 0..height,
+            sums: vec![Vec3::default(); width * height],
+            weights: vec![0.0; width * height],
+            stats: vec![PixelStats::default(); width * height],
+        }
+    }
+
+    /// An empty film for the pixels `x` by `y` of this film's image, widened
+    /// by the filter radius so it takes every splat of samples drawn inside
+    /// them. `merge` adds it back.
+    pub fn tile(&self, x: Range<usize>, y: Range<usize>) -> Film {
+        let margin = (self.filter.radius() - 0.5).ceil().max(0.0) as usize;
+        let x = x.start.saturating_sub(margin)..(x.end + margin).min(self.width);
+        let y = y.start.saturating_sub(margin)..(y.end + margin).min(self.height);
+        let len = x.len() * y.len();
+        Film {
+            x,
+            y,
+            sums: vec![Vec3::default(); len],
+            weights: vec![0.0; len],
+            stats: vec![PixelStats::default(); len],
+            ..*self
+        }
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// Index of image pixel `(i, j)` in this film's buffers.
+    fn index(&self, i: usize, j: usize) -> usize {
+        (j - self.y.start) * self.x.len() + i - self.x.start
+    }
+
+    /// Number of samples drawn inside image pixel `(i, j)`, which must lie
+    /// on this film.
+    pub fn samples(&self, i: usize, j: usize) -> u32 {
+        self.stats[self.index(i, j)].count
+    }
+
+    /// Estimated error of pixel `(i, j)`: the standard error of its mean
+    /// luminance relative to that mean. Pixels darker than 0.1 count as
+    /// 0.1, so that noise in near-black pixels, which nobody can see,
+    /// does not look enormous. Infinite below two samples.
+    pub fn relative_error(&self, i: usize, j: usize) -> f32 {
+        self.standard_error(i, j) / self.stats[self.index(i, j)].mean.max(0.1)
+    }
+
+    /// Standard error of the mean luminance of pixel `(i, j)`, infinite
+    /// below two samples.
+    pub fn standard_error(&self, i: usize, j: usize) -> f32 {
+        let stats = self.stats[self.index(i, j)];
+        if stats.count < 2 {
+            return f32::INFINITY;
+        }
+        let n = stats.count as f32;
+        (stats.m2.max(0.0) / (n - 1.0) / n).sqrt()
+    }
+
+    /// Pixels of `range` whose centres are within the filter radius of
+    /// `p`, more precisely in `(p - radius, p + radius]`.
+    fn reach(&self, p: f32, range: &Range<usize>) -> Range<usize> {
+        let radius = self.filter.radius();
+        let first = (p - radius - 0.5).floor() as i64 + 1;
+        let last = (p + radius - 0.5).floor() as i64;
+        let clip = |k: i64| k.clamp(range.start as i64, range.end as i64) as usize;
+        clip(first)..clip(last + 1)
+    }
+
+    /// Splats `radiance`, seen through raster position `(x, y)`, onto the
+    /// pixels the filter reaches.
+    pub fn add_sample(&mut self, x: f32, y: f32, radiance: Vec3) {
+        let (i, j) = (x.floor(), y.floor());
+        if i >= 0.0 && j >= 0.0 && self.x.contains(&(i as usize)) && self.y.contains(&(j as usize)) {
+            let k = self.index(i as usize, j as usize);
+            self.stats[k].add(luminance(radiance));
+        }
+        let columns = self.reach(x, &self.x);
+        for j in self.reach(y, &self.y) {
+            let wy = self.filter.evaluate(y - (j as f32 + 0.5));
+            if wy == 0.0 {
+                continue;
+            }
+            for i in columns.clone() {
+                let w = wy * self.filter.evaluate(x - (i as f32 + 0.5));
+                let k = self.index(i, j);
+                self.sums[k] += radiance * w;
+                self.weights[k] += w;
+            }
+        }
+    }
+
+    /// Adds the samples of `tile`, a film from `tile()` of the same image.
+    pub fn merge(&mut self, tile: &Film) {
+        assert!((self.width, self.height) == (tile.width, tile.height), "tile is from another image");
+        for j in tile.y.clone() {
+            let src = tile.index(tile.x.start, j);
+            let dst = self.index(tile.x.start, j);
+            for n in 0..tile.x.len() {
+                self.sums[dst + n] += tile.sums[src + n];
+                self.weights[dst + n] += tile.weights[src + n];
+                self.stats[dst + n].combine(&tile.stats[src + n]);
+            }
+        }
+    }
+
+    /// The reconstructed image: every pixel's weighted average. Pixels
+    /// without samples are black.
+    pub fn to_framebuffer(&self) -> Framebuffer {
+        let pixels = self.sums.iter().zip(self.weights.iter())
+            .map(|(&sum, &weight)| if weight != 0.0 { sum / weight } else { Vec3::default() })
+            .collect();
+        Framebuffer::from_pixels(self.x.len(), self.y.len(), pixels)
+    }
+
+    /// Gray image of how many samples each pixel got, scaled so that the
+    /// most sampled pixel is white.
+    pub fn sample_map(&self) -> Framebuffer {
+        let most = self.stats.iter().map(|s| s.count).max().unwrap_or(0).max(1) as f32;
+        let pixels = self.stats.iter().map(|s| {
+            let v = s.count as f32 / most;
+            Vec3::new(v, v, v)
+        }).collect();
+        Framebuffer::from_pixels(self.x.len(), self.y.len(), pixels)
+    }
+
+    /// Writes the film exactly as it is, sample statistics included, in a
+    /// little-endian binary layout that `read` restores bit for bit.
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let (tag, params) = self.filter.encode();
+        out.write_all(&[tag])?;
+        for p in params.iter() {
+            out.write_all(&p.to_le_bytes())?;
+        }
+        for n in [self.width, self.height, self.x.start, self.x.end, self.y.start, self.y.end].iter() {
+            out.write_all(&(*n as u64).to_le_bytes())?;
+        }
+        for k in 0..self.sums.len() {
+            let (sum, stats) = (self.sums[k], self.stats[k]);
+            for v in [sum.x, sum.y, sum.z, self.weights[k]].iter() {
+                out.write_all(&v.to_le_bytes())?;
+            }
+            out.write_all(&stats.count.to_le_bytes())?;
+            out.write_all(&stats.mean.to_le_bytes())?;
+            out.write_all(&stats.m2.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read(input: &mut dyn Read) -> io::Result<Film> {
+        let mut tag = [0];
+        input.read_exact(&mut tag)?;
+        let params = [read_f32(input)?, read_f32(input)?, read_f32(input)?];
+        let filter = Filter::decode(tag[0], params).ok_or_else(|| invalid("unknown filter"))?;
+        let mut n = [0; 6];
+        for v in n.iter_mut() {
+            *v = read_u64(input)? as usize;
+        }
+        let [width, height, x0, x1, y0, y1] = n;
+        if x0 > x1 || x1 > width || y0 > y1 || y1 > height {
+            return Err(invalid("film region lies outside the image"));
+        }
+        let mut film = Film {
+            width,
+            height,
+            filter,
+            x: x0..x1,
+            y: y0..y1,
+            sums: Vec::new(),
+            weights: Vec::new(),
+            stats: Vec::new(),
+        };
+        for _ in 0..(x1 - x0) * (y1 - y0) {
+            let sum = Vec3::new(read_f32(input)?, read_f32(input)?, read_f32(input)?);
+            film.sums.push(sum);
+            film.weights.push(read_f32(input)?);
+            let mut count = [0; 4];
+            input.read_exact(&mut count)?;
+            let count = u32::from_le_bytes(count);
+            film.stats.push(PixelStats { count, mean: read_f32(input)?, m2: read_f32(input)? });
+        }
+        Ok(film)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::{Pcg32, Sampler};
+
+    const FILTERS: [&str; 5] = ["box", "tent", "gaussian", "mitchell", "lanczos"];
+
+    fn splat_uniformly(film: &mut Film, width: usize, height: usize, color: impl Fn(f32, f32) -> Vec3) {
+        let mut sampler = Pcg32::new(4, 0);
+        for _ in 0..width * height * 64 {
+            let x = sampler.next_f32() * width as f32;
+            let y = sampler.next_f32() * height as f32;
+            film.add_sample(x, y, color(x, y));
+        }
+    }
+
+    #[test]
+    fn weights_are_normalized() {
+        for name in FILTERS.iter() {
+            let mut film = Film::new(6, 4, Filter::named(name).unwrap());
+            splat_uniformly(&mut film, 6, 4, |_, _| Vec3::new(0.25, 0.5, 1.0));
+            for p in film.to_framebuffer().pixels() {
+                assert!((p.x - 0.25).abs() < 1e-4 && (p.z - 1.0).abs() < 1e-4, "{}: {:?}", name, p);
+            }
+        }
+    }
+
+    #[test]
+    fn only_wide_filters_spread_samples() {
+        for name in FILTERS.iter() {
+            let mut film = Film::new(5, 5, Filter::named(name).unwrap());
+            film.add_sample(2.7, 2.5, Vec3::new(1., 1., 1.));
+            let image = film.to_framebuffer();
+            assert_eq!(image.get(2, 2).x, 1.0, "{}", name);
+            assert_eq!(image.get(3, 2).x > 0.0, *name != "box", "{}", name);
+        }
+    }
+
+    #[test]
+    fn tiles_add_up_to_the_whole_image() {
+        let filter = Filter::named("mitchell").unwrap();
+        let color = |x: f32, y: f32| Vec3::new(x, y, 1.0);
+        let mut whole = Film::new(8, 6, filter);
+        splat_uniformly(&mut whole, 8, 6, color);
+
+        let mut merged = Film::new(8, 6, filter);
+        let mut tiles = [merged.tile(0..4, 0..6), merged.tile(4..8, 0..6)];
+        let mut sampler = Pcg32::new(4, 0);
+        for _ in 0..8 * 6 * 64 {
+            let x = sampler.next_f32() * 8.0;
+            let y = sampler.next_f32() * 6.0;
+            tiles[(x >= 4.0) as usize].add_sample(x, y, color(x, y));
+        }
+        for tile in tiles.iter() {
+            merged.merge(tile);
+        }
+        for (p, q) in whole.to_framebuffer().pixels().iter().zip(merged.to_framebuffer().pixels()) {
+            assert!((*p - *q).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn error_shrinks_with_samples_and_survives_merging() {
+        let mut whole = Film::new(2, 1, Filter::default());
+        let mut halves = [whole.tile(0..2, 0..1), whole.tile(0..2, 0..1)];
+        let mut sampler = Pcg32::new(8, 0);
+        for n in 0..4000 {
+            let gray = sampler.next_f32();
+            let c = Vec3::new(gray, gray, gray);
+            let x = if n < 100 { 0.5 } else { 1.5 };
+            whole.add_sample(x, 0.5, c);
+            halves[n % 2].add_sample(x, 0.5, c);
+        }
+        assert_eq!((whole.samples(0, 0), whole.samples(1, 0)), (100, 3900));
+        // Uniform numbers have variance 1/12 around a mean of 1/2.
+        let expected = |n: f32| (1.0f32 / 12.0 / n).sqrt() / 0.5;
+        assert!((whole.relative_error(0, 0) / expected(100.0) - 1.0).abs() < 0.15);
+        assert!((whole.relative_error(1, 0) / expected(3900.0) - 1.0).abs() < 0.05);
+
+        let mut merged = Film::new(2, 1, Filter::default());
+        merged.merge(&halves[0]);
+        merged.merge(&halves[1]);
+        for i in 0..2 {
+            assert_eq!(merged.samples(i, 0), whole.samples(i, 0));
+            assert!((merged.relative_error(i, 0) / whole.relative_error(i, 0) - 1.0).abs() < 1e-3);
+        }
+        assert_eq!(merged.sample_map().get(1, 0).x, 1.0);
+    }
+}
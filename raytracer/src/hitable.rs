@@ -0,0 +1,547 @@
+use crate::myvec::Vec3;
+use crate::ray::Ray;
+use crate::aabb::Aabb;
+use crate::material::{Material, Lambertian, Metal, Dielectric, DiffuseLight};
+use crate::rect::{Cuboid, FlipNormals, XYRect, XZRect, YZRect};
+use crate::instance::TransformedInstance;
+use crate::medium::ConstantMedium;
+use crate::transform::Transform;
+use std::sync::Arc;
+use crate::sampler::Sampler;
+
+pub struct HitRecord {
+    pub t: f32,
+    pub p: Vec3,
+    pub normal: Vec3,
+    /// Surface coordinates of `p`, each in `[0, 1]`.
+    pub u: f32,
+    pub v: f32,
+    pub material: Arc<dyn Material>,
+    /// ID of the object hit, as given by a `Tagged` around it, or 0.
+    pub object_id: u32,
+}
+
+pub trait Hitable: Send + Sync {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    /// Box enclosing the object at every instant in `[time0, time1]`, or
+    /// `None` if it is unbounded.
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb>;
+
+    /// Density, per unit solid angle around `origin`, with which `random`
+    /// picks `direction`. Objects that cannot be sampled as lights return 0.
+    fn pdf_value(&self, _origin: Vec3, _direction: Vec3) -> f32 {
+        0.0
+    }
+
+    /// Direction from `origin` towards a random point of the object. Only
+    /// meaningful where `pdf_value` is not always 0.
+    fn random(&self, _origin: Vec3, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1., 0., 0.)
+    }
+}
+
+/// Lets one object sit in several lists, e.g. both in the world and among
+/// the lights.
+impl<T: Hitable + ?Sized> Hitable for Arc<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        (**self).bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        (**self).random(origin, sampler)
+    }
+}
+
+/// Solid-angle density of reaching a surface point picked uniformly over
+/// `area`, when a ray along `direction` hits it at `t` where its geometric
+/// normal is `normal`.
+pub(crate) fn area_pdf(direction: Vec3, t: f32, normal: Vec3, area: f32) -> f32 {
+    let cosine = (direction.dot(normal) / (direction.length() * normal.length())).abs();
+    if cosine <= 0.0 || area <= 0.0 {
+        return 0.0;
+    }
+    let distance_squared = t * t * direction.square();
+    distance_squared / (cosine * area)
+}
+
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32, material: Arc<dyn Material>) -> Self {
+        Sphere { center, radius, material }
+    }
+}
+
+/// Longitude/latitude coordinates of a point `d` on the unit sphere.
+pub fn sphere_uv(d: Vec3) -> (f32, f32) {
+    let phi = d.z.atan2(d.x);
+    let theta = d.y.clamp(-1.0, 1.0).asin();
+    let u = 1.0 - (phi + std::f32::consts::PI) / (2.0 * std::f32::consts::PI);
+    let v = (theta + std::f32::consts::FRAC_PI_2) / std::f32::consts::PI;
+    (u, v)
+}
+
+fn hit_sphere(center: Vec3, radius: f32, material: &Arc<dyn Material>, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    let oc = r.origin - center;
+    let a = r.direction.dot(r.direction);
+    let b = oc.dot(r.direction);
+    let c = oc.dot(oc) - radius * radius;
+    let discriminant = b * b - a * c;
+    if discriminant > 0.0 {
+        let temp = (-b - (b*b-a*c).sqrt())/a;
+        if temp < t_max && temp > t_min {
+            let t = temp;
+            let p = r.point_at_paramter(t);
+            let normal = (p - center) / radius;
+            let (u, v) = sphere_uv((p - center) / radius.abs());
+            return Some(HitRecord {t, p, normal, u, v, material: Arc::clone(material), object_id: 0});
+        }
+        let temp = (-b + (b*b-a*c).sqrt())/a;
+        if temp < t_max && temp > t_min {
+            let t = temp;
+            let p = r.point_at_paramter(t);
+            let normal = (p - center) / radius;
+            let (u, v) = sphere_uv((p - center) / radius.abs());
+            return Some(HitRecord{t, p, normal, u, v, material: Arc::clone(material), object_id: 0});
+        }
+    }
+    None
+}
+
+impl Hitable for Sphere {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_sphere(self.center, self.radius, &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let extent = Vec3::new(r, r, r);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    /// Uniform over the cone of directions in which the sphere is seen.
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        let distance_squared = (self.center - origin).square();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared || self.hit(&Ray::new(origin, direction), 0.001, f32::MAX).is_none() {
+            return 0.0;
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        1.0 / (2.0 * std::f32::consts::PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let to_center = self.center - origin;
+        let distance_squared = to_center.square();
+        let w = to_center / distance_squared.sqrt();
+        let (u, v) = w.basis();
+        let cos_theta_max = (1.0 - (self.radius * self.radius / distance_squared).min(1.0)).sqrt();
+        let z = 1.0 + sampler.next_f32() * (cos_theta_max - 1.0);
+        let phi = 2.0 * std::f32::consts::PI * sampler.next_f32();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        u * (r * phi.cos()) + v * (r * phi.sin()) + w * z
+    }
+}
+
+/// Path of a `MovingSphere`'s center over time.
+#[derive(Debug, Clone)]
+pub enum Motion {
+    /// Straight line through `center0` at `time0` and `center1` at `time1`,
+    /// continued beyond both ends.
+    Linear { center0: Vec3, center1: Vec3, time0: f32, time1: f32 },
+    /// `(time, center)` pairs sorted by time, joined by straight lines.
+    /// The center rests at the first and last key outside their range.
+    Keyframes(Vec<(f32, Vec3)>),
+}
+
+impl Motion {
+    pub fn center(&self, time: f32) -> Vec3 {
+        match self {
+            Motion::Linear { center0, center1, time0, time1 } => {
+                if time1 == time0 {
+                    return *center0;
+                }
+                *center0 + (*center1 - *center0) * ((time - time0) / (time1 - time0))
+            }
+            Motion::Keyframes(keys) => {
+                let next = keys.partition_point(|&(t, _)| t <= time);
+                if next == 0 {
+                    return keys[0].1;
+                }
+                if next == keys.len() {
+                    return keys[next - 1].1;
+                }
+                let (t0, c0) = keys[next - 1];
+                let (t1, c1) = keys[next];
+                c0 + (c1 - c0) * ((time - t0) / (t1 - t0))
+            }
+        }
+    }
+
+    /// Times in `[time0, time1]` at which the center can be furthest out:
+    /// the ends of the interval and any keyframe inside it.
+    fn extremes(&self, time0: f32, time1: f32) -> Vec<f32> {
+        let mut times = vec![time0, time1];
+        if let Motion::Keyframes(keys) = self {
+            times.extend(keys.iter().map(|&(t, _)| t).filter(|&t| t > time0 && t < time1));
+        }
+        times
+    }
+}
+
+/// Sphere whose center follows a `Motion`; rays see it where it is at their
+/// `time`, which blurs it over the camera's shutter interval.
+pub struct MovingSphere {
+    pub motion: Motion,
+    pub radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new(center0: Vec3, center1: Vec3, time0: f32, time1: f32, radius: f32, material: Arc<dyn Material>) -> Self {
+        MovingSphere { motion: Motion::Linear { center0, center1, time0, time1 }, radius, material }
+    }
+
+    /// Panics unless `keys` is non-empty and sorted by time.
+    pub fn keyframed(keys: Vec<(f32, Vec3)>, radius: f32, material: Arc<dyn Material>) -> Self {
+        assert!(!keys.is_empty(), "a keyframed sphere needs at least one key");
+        assert!(keys.windows(2).all(|w| w[0].0 < w[1].0), "keys must be sorted by time");
+        MovingSphere { motion: Motion::Keyframes(keys), radius, material }
+    }
+}
+
+impl Hitable for MovingSphere {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_sphere(self.motion.center(r.time), self.radius, &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let extent = Vec3::new(r, r, r);
+        let mut bbox = Aabb::empty();
+        for t in self.motion.extremes(time0, time1) {
+            let center = self.motion.center(t);
+            bbox = bbox.union(&Aabb::new(center - extent, center + extent));
+        }
+        Some(bbox)
+    }
+}
+
+#[derive(Default)]
+pub struct HitableList {
+    pub list: Vec<Box<dyn Hitable>>,
+}
+
+impl HitableList {
+    pub fn add(&mut self, hitable: Box<dyn Hitable>) {
+        self.list.push(hitable);
+    }
+
+    pub fn length(&self) -> usize {
+        self.list.len()
+    }
+
+    /// The same objects, each in a `Tagged` with its position in the list,
+    /// counting from 1, as ID.
+    pub fn tagged(self) -> HitableList {
+        let list = self.list.into_iter().enumerate()
+            .map(|(n, hitable)| Box::new(Tagged::new(hitable, n as u32 + 1)) as Box<dyn Hitable>)
+            .collect();
+        HitableList { list }
+    }
+}
+
+/// Stamps `id` on the hits of the object inside, so that renders can tell
+/// objects apart. An outer tag replaces inner ones.
+pub struct Tagged {
+    object: Box<dyn Hitable>,
+    id: u32,
+}
+
+impl Tagged {
+    pub fn new(object: Box<dyn Hitable>, id: u32) -> Self {
+        Tagged { object, id }
+    }
+}
+
+impl Hitable for Tagged {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.object.hit(r, t_min, t_max).map(|rec| HitRecord { object_id: self.id, ..rec })
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(origin, sampler)
+    }
+}
+
+impl Hitable for HitableList {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut closest_so_far = t_max;
+        let mut closest: Option<HitRecord> = None;
+        for hitable in self.list.iter() {
+            if let Some(rec) =  hitable.hit(r,t_min, closest_so_far) {
+                closest_so_far = rec.t;
+                closest = Some(rec);
+            }
+        }
+        closest
+    }
+
+    /// Picks a member uniformly, so the density is the members' average.
+    /// `random` must not be called on an empty list.
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        if self.list.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = self.list.iter().map(|hitable| hitable.pdf_value(origin, direction)).sum();
+        sum / self.list.len() as f32
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let n = self.list.len();
+        let index = ((sampler.next_f32() * n as f32) as usize).min(n - 1);
+        self.list[index].random(origin, sampler)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        let mut bbox = Aabb::empty();
+        for hitable in self.list.iter() {
+            bbox = bbox.union(&hitable.bounding_box(time0, time1)?);
+        }
+        Some(bbox)
+    }
+}
+
+/// The final scene of the book: a field of small random spheres around
+/// three big ones.
+pub fn random_scene(sampler: &mut dyn Sampler) -> HitableList {
+    spheres(sampler, false)
+}
+
+/// `random_scene` with the small diffuse spheres bouncing upwards between
+/// times 0 and 1, for motion blur.
+pub fn bouncing_spheres(sampler: &mut dyn Sampler) -> HitableList {
+    spheres(sampler, true)
+}
+
+fn spheres(sampler: &mut dyn Sampler, bouncing: bool) -> HitableList {
+    let mut list = HitableList::default();
+    let sphere = Sphere::new(Vec3::new(0., -1000., 0.), 1000.,
+            Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))));
+    list.add(Box::new(sphere));
+    
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Vec3::new(a as f32 + 0.9 * sampler.next_f32(), 0.2, b as f32 + 0.9 * sampler.next_f32());
+            if (center - Vec3::new(4., 0.2, 0.)).length() > 0.9 {
+                let random = sampler.next_f32();
+                let hitable: Box<dyn Hitable> =
+                    if random < 0.8 {
+                        let r = sampler.next_f32() * sampler.next_f32();
+                        let g = sampler.next_f32() * sampler.next_f32();
+                        let b = sampler.next_f32() * sampler.next_f32();
+                        let material = Arc::new(Lambertian::new(Vec3::new(r, g, b)));
+                        if bouncing {
+                            let center1 = center + Vec3::new(0., 0.5 * sampler.next_f32(), 0.);
+                            Box::new(MovingSphere::new(center, center1, 0.0, 1.0, 0.2, material))
+                        } else {
+                            Box::new(Sphere::new(center, 0.2, material))
+                        }
+                    } else if random < 0.95 {
+                        let x = 0.5 * (1.0 + sampler.next_f32());
+                        let y = 0.5 * (1.0 + sampler.next_f32());
+                        let z = 0.5 * (1.0 + sampler.next_f32());
+                        let fuzz = 0.5 * sampler.next_f32();
+                        Box::new(Sphere::new(center, 0.2,
+                            Arc::new(Metal::new(Vec3::new(x, y, z), fuzz))))
+                    } else {
+                        Box::new(Sphere::new(center, 0.2,
+                            Arc::new(Dielectric::new(1.5))))
+                    };
+                list.add(hitable);
+            }
+        }
+    }
+    
+    list.add(Box::new(Sphere::new(Vec3::new(0., 1., 0.), 1.0, 
+                Arc::new(Dielectric::new(1.5)))));
+
+    list.add(Box::new(Sphere::new(Vec3::new(-4., -1., 0.), 1.0,
+                    Arc::new(Lambertian::new(Vec3::new(0.4, 0.2, 0.1))))));
+
+    list.add(Box::new(Sphere::new(Vec3::new(4., 1., 0.), 1.0,  
+                    Arc::new(Metal::new(Vec3::new(0.7, 0.6, 0.5), 0.0)))));            
+    
+    list
+}
+/// The Cornell box: a 555-unit room with a red left wall, a green right
+/// wall, a ceiling light and two white blocks turned towards each other.
+/// Meant to be seen from `(278, 278, -800)` looking at `(278, 278, 0)` with a
+/// 40° field of view and a black background. Returns the room and, for
+/// light sampling, its light.
+pub fn cornell_box() -> (HitableList, HitableList) {
+    cornell(false)
+}
+
+/// The Cornell box with the blocks replaced by black and white smoke, lit by
+/// a larger and dimmer ceiling light.
+pub fn cornell_smoke() -> (HitableList, HitableList) {
+    cornell(true)
+}
+
+fn cornell(smoke: bool) -> (HitableList, HitableList) {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3::new(0.12, 0.45, 0.15)));
+
+    let mut list = HitableList::default();
+    // The camera looks down +z, so x = 555 is on the left of the image.
+    list.add(Box::new(FlipNormals::new(Box::new(YZRect::new(0., 555., 0., 555., 555., red)))));
+    list.add(Box::new(YZRect::new(0., 555., 0., 555., 0., green)));
+    let light = if smoke {
+        Arc::new(XZRect::new(113., 443., 127., 432., 554., Arc::new(DiffuseLight::new(Vec3::new(7., 7., 7.)))))
+    } else {
+        Arc::new(XZRect::new(213., 343., 227., 332., 554., Arc::new(DiffuseLight::new(Vec3::new(15., 15., 15.)))))
+    };
+    list.add(Box::new(Arc::clone(&light)));
+    let mut lights = HitableList::default();
+    lights.add(Box::new(light));
+    list.add(Box::new(FlipNormals::new(Box::new(XZRect::new(0., 555., 0., 555., 555., Arc::clone(&white))))));
+    list.add(Box::new(XZRect::new(0., 555., 0., 555., 0., Arc::clone(&white))));
+    list.add(Box::new(FlipNormals::new(Box::new(XYRect::new(0., 555., 0., 555., 555., Arc::clone(&white))))));
+
+    let short_block = Cuboid::new(Vec3::new(0., 0., 0.), Vec3::new(165., 165., 165.), Arc::clone(&white));
+    let tall_block = Cuboid::new(Vec3::new(0., 0., 0.), Vec3::new(165., 330., 165.), white);
+    let short_block: Box<dyn Hitable> = Box::new(TransformedInstance::new(Arc::new(short_block),
+        Transform::translate(Vec3::new(130., 0., 65.)) * Transform::rotate_y(-18.0)));
+    let tall_block: Box<dyn Hitable> = Box::new(TransformedInstance::new(Arc::new(tall_block),
+        Transform::translate(Vec3::new(265., 0., 295.)) * Transform::rotate_y(15.0)));
+    if smoke {
+        list.add(Box::new(ConstantMedium::new(short_block, 0.01, Vec3::new(1., 1., 1.))));
+        list.add(Box::new(ConstantMedium::new(tall_block, 0.01, Vec3::new(0., 0., 0.))));
+    } else {
+        list.add(short_block);
+        list.add(tall_block);
+    }
+    (list, lights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphere(center: Vec3, radius: f32) -> Sphere {
+        Sphere::new(center, radius, Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))))
+    }
+
+    #[test]
+    fn sphere_hit_returns_nearest_root() {
+        let s = sphere(Vec3::new(0., 0., -2.), 0.5);
+        let r = Ray::new(Vec3::default(), Vec3::new(0., 0., -1.));
+        let rec = s.hit(&r, 0.0, f32::MAX).unwrap();
+        assert!((rec.t - 1.5).abs() < 1e-6);
+        assert!((rec.normal.z - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sphere_hit_from_inside_returns_far_root() {
+        let s = sphere(Vec3::default(), 1.0);
+        let r = Ray::new(Vec3::default(), Vec3::new(1., 0., 0.));
+        let rec = s.hit(&r, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn list_returns_closest_hit() {
+        let mut list = HitableList::default();
+        list.add(Box::new(sphere(Vec3::new(0., 0., -5.), 0.5)));
+        list.add(Box::new(sphere(Vec3::new(0., 0., -2.), 0.5)));
+        let r = Ray::new(Vec3::default(), Vec3::new(0., 0., -1.));
+        let rec = list.hit(&r, 0.0, f32::MAX).unwrap();
+        assert!((rec.t - 1.5).abs() < 1e-6);
+        assert!(list.hit(&r, 0.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn moving_sphere_is_hit_where_it_is_at_ray_time() {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let s = MovingSphere::new(Vec3::new(0., 0., -2.), Vec3::new(0., 2., -2.), 0.0, 1.0, 0.5, material);
+        let r = |time| Ray::with_time(Vec3::new(0., 1., 0.), Vec3::new(0., 0., -1.), time);
+        assert!(s.hit(&r(0.0), 0.0, f32::MAX).is_none());
+        assert!((s.hit(&r(0.5), 0.0, f32::MAX).unwrap().t - 1.5).abs() < 1e-6);
+        let bbox = s.bounding_box(0.0, 1.0).unwrap();
+        assert_eq!((bbox.min.y, bbox.max.y), (-0.5, 2.5));
+    }
+
+    #[test]
+    fn keyframes_bound_every_key_in_the_interval() {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let keys = vec![(0.0, Vec3::new(0., 0., 0.)), (0.5, Vec3::new(4., 0., 0.)), (1.0, Vec3::new(0., 0., 0.))];
+        let s = MovingSphere::keyframed(keys, 1.0, material);
+        assert_eq!(s.motion.center(0.25).x, 2.0);
+        assert_eq!(s.motion.center(2.0).x, 0.0);
+        assert_eq!(s.bounding_box(0.0, 1.0).unwrap().max.x, 5.0);
+        assert_eq!(s.bounding_box(0.0, 0.25).unwrap().max.x, 3.0);
+    }
+
+    #[test]
+    fn light_pdfs_integrate_to_one() {
+        use crate::sampler::{random_unit_vector, Pcg32};
+        let mut lights = HitableList::default();
+        lights.add(Box::new(sphere(Vec3::new(0., 3., 0.), 1.0)));
+        lights.add(Box::new(XZRect::new(-1., 2., -1., 1., 2., Arc::new(Lambertian::new(Vec3::default())))));
+        let origin = Vec3::new(0.5, 0., 0.);
+        let mut sampler = Pcg32::new(1, 0);
+
+        // Averaging pdf / (1 / 4π) over uniform directions estimates the
+        // integral of the pdf over the sphere of directions.
+        let n = 200_000;
+        let total: f32 = (0..n).map(|_| lights.pdf_value(origin, random_unit_vector(&mut sampler))).sum();
+        let integral = total / n as f32 * 4.0 * std::f32::consts::PI;
+        assert!((integral - 1.0).abs() < 0.03, "{}", integral);
+
+        for _ in 0..100 {
+            let direction = lights.random(origin, &mut sampler);
+            assert!(lights.pdf_value(origin, direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn cornell_box_has_red_on_the_left_and_green_on_the_right() {
+        use crate::camera::Camera;
+        use crate::sampler::Pcg32;
+        let (world, _) = cornell_box();
+        let camera = Camera::new(Vec3::new(278., 278., -800.), Vec3::new(278., 278., 0.), Vec3::new(0., 1., 0.),
+                                 40.0, 1.0, 0.0, 10.0);
+        let mut sampler = Pcg32::new(0, 0);
+        let wall = |s: f32, sampler: &mut Pcg32| {
+            let r = camera.get_ray(s, 0.5, sampler);
+            let rec = world.hit(&r, 0.001, f32::MAX).unwrap();
+            rec.material.albedo(&rec)
+        };
+        let left = wall(0.1, &mut sampler);
+        let right = wall(0.9, &mut sampler);
+        assert!(left.x > 0.5 && left.y < 0.1, "{:?}", left);
+        assert!(right.y > 0.4 && right.x < 0.2, "{:?}", right);
+    }
+}
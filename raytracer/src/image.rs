@@ -0,0 +1,372 @@
+use crate::myvec::Vec3;
+use crate::tonemap::{srgb_decode, OutputSettings};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Linear RGB image, stored row by row with the top row first.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer { width, height, pixels: vec![Vec3::default(); width * height] }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count does not match {}x{}", width, height);
+        Framebuffer { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Vec3) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Clamped 8-bit sRGB triplets, top row first.
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.to_rgb8_with(&OutputSettings::default())
+    }
+
+    /// 8-bit sRGB triplets after the exposure, tone mapping and dithering
+    /// of `output`, top row first.
+    pub fn to_rgb8_with(&self, output: &OutputSettings) -> Vec<u8> {
+        output.quantize(&self.pixels)
+    }
+}
+
+pub trait ImageWriter {
+    fn write(&self, image: &Framebuffer, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// ASCII `P3` PPM.
+#[derive(Default)]
+pub struct PpmAsciiWriter {
+    pub output: OutputSettings,
+}
+
+/// Binary `P6` PPM.
+#[derive(Default)]
+pub struct PpmWriter {
+    pub output: OutputSettings,
+}
+
+/// 8-bit sRGB PNG.
+#[derive(Default)]
+pub struct PngWriter {
+    pub output: OutputSettings,
+}
+
+/// Radiance RGBE `.hdr`, linear floats. Output settings do not apply: the
+/// image is stored as rendered.
+pub struct HdrWriter;
+
+/// Portable float map `.pfm`, linear floats, stored as rendered.
+pub struct PfmWriter;
+
+impl ImageWriter for PpmAsciiWriter {
+    fn write(&self, image: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "P3\n{} {}\n255\n", image.width, image.height)?;
+        for rgb in image.to_rgb8_with(&self.output).chunks(3) {
+            writeln!(out, "{} {} {}", rgb[0], rgb[1], rgb[2])?;
+        }
+        Ok(())
+    }
+}
+
+impl ImageWriter for PpmWriter {
+    fn write(&self, image: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", image.width, image.height)?;
+        out.write_all(&image.to_rgb8_with(&self.output))
+    }
+}
+
+impl ImageWriter for PngWriter {
+    fn write(&self, image: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(out, image.width as u32, image.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&image.to_rgb8_with(&self.output))?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+fn rgbe(col: Vec3) -> [u8; 4] {
+    let v = col.x.max(col.y).max(col.z);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let e = v.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f32.powi(e);
+    [
+        (col.x.max(0.0) * scale) as u8,
+        (col.y.max(0.0) * scale) as u8,
+        (col.z.max(0.0) * scale) as u8,
+        (e + 128) as u8,
+    ]
+}
+
+impl ImageWriter for HdrWriter {
+    fn write(&self, image: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", image.height, image.width)?;
+        let mut bytes = Vec::with_capacity(image.pixels.len() * 4);
+        for col in image.pixels.iter() {
+            bytes.extend_from_slice(&rgbe(*col));
+        }
+        out.write_all(&bytes)
+    }
+}
+
+impl ImageWriter for PfmWriter {
+    fn write(&self, image: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+        // A negative scale marks little-endian data; rows go bottom to top.
+        write!(out, "PF\n{} {}\n-1.0\n", image.width, image.height)?;
+        let mut bytes = Vec::with_capacity(image.pixels.len() * 12);
+        for row in image.pixels.chunks(image.width).rev() {
+            for col in row {
+                bytes.extend_from_slice(&col.x.to_le_bytes());
+                bytes.extend_from_slice(&col.y.to_le_bytes());
+                bytes.extend_from_slice(&col.z.to_le_bytes());
+            }
+        }
+        out.write_all(&bytes)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    PpmAscii,
+    Ppm,
+    Png,
+    Hdr,
+    Pfm,
+}
+
+impl ImageFormat {
+    /// The format called `ppm` (binary P6), `ppm-ascii` (P3), `png`, `hdr`
+    /// or `pfm`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "ppm" => Some(ImageFormat::Ppm),
+            "ppm-ascii" => Some(ImageFormat::PpmAscii),
+            "png" => Some(ImageFormat::Png),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+
+    /// Picks the format from the file extension: `.ppm` (binary P6), `.png`,
+    /// `.hdr` or `.pfm`. ASCII PPM is only written when asked for by name.
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("ppm") => Ok(ImageFormat::Ppm),
+            Some("png") => Ok(ImageFormat::Png),
+            Some("hdr") => Ok(ImageFormat::Hdr),
+            Some("pfm") => Ok(ImageFormat::Pfm),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("unsupported image extension: {}", path.display()))),
+        }
+    }
+
+    /// A writer for this format; `output` only matters to the 8-bit ones.
+    pub fn writer(self, output: OutputSettings) -> Box<dyn ImageWriter> {
+        match self {
+            ImageFormat::PpmAscii => Box::new(PpmAsciiWriter { output }),
+            ImageFormat::Ppm => Box::new(PpmWriter { output }),
+            ImageFormat::Png => Box::new(PngWriter { output }),
+            ImageFormat::Hdr => Box::new(HdrWriter),
+            ImageFormat::Pfm => Box::new(PfmWriter),
+        }
+    }
+}
+
+/// Writes `image` to `path` in the format named by its extension, clamped
+/// to sRGB without any tone mapping.
+pub fn save(image: &Framebuffer, path: impl AsRef<Path>) -> io::Result<()> {
+    save_with(image, path, &OutputSettings::default())
+}
+
+/// Like `save`, with the exposure and tone mapping of `output`.
+pub fn save_with(image: &Framebuffer, path: impl AsRef<Path>, output: &OutputSettings) -> io::Result<()> {
+    save_as(image, path.as_ref(), ImageFormat::from_path(path.as_ref())?, output)
+}
+
+pub fn save_as(image: &Framebuffer, path: impl AsRef<Path>, format: ImageFormat, output: &OutputSettings) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    format.writer(*output).write(image, &mut out)?;
+    out.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Inverse of quantization, up to the rounding.
+fn dequantize(c: u32, max: u32) -> f32 {
+    srgb_decode(c as f32 / max as f32)
+}
+
+/// Reads a PPM (`P3` or `P6`) or PNG image, recognised by its first bytes.
+/// Values are taken to be sRGB and decoded back to linear.
+pub fn load_image(path: impl AsRef<Path>) -> io::Result<Framebuffer> {
+    read_image(&fs::read(path)?)
+}
+
+pub fn read_image(data: &[u8]) -> io::Result<Framebuffer> {
+    if data.starts_with(b"\x89PNG") {
+        read_png(data)
+    } else if data.starts_with(b"P3") || data.starts_with(b"P6") {
+        read_ppm(data)
+    } else {
+        Err(invalid("not a PPM or PNG image"))
+    }
+}
+
+fn read_ppm(data: &[u8]) -> io::Result<Framebuffer> {
+    // Header: magic, width, height and maxval, separated by whitespace and
+    // `#` comments; binary data starts after one more whitespace byte.
+    let mut pos = 2;
+    let mut header = [0u32; 3];
+    for value in header.iter_mut() {
+        loop {
+            match data.get(pos) {
+                Some(b'#') => while data.get(pos).is_some_and(|&c| c != b'\n') { pos += 1 },
+                Some(c) if c.is_ascii_whitespace() => pos += 1,
+                _ => break,
+            }
+        }
+        let start = pos;
+        while data.get(pos).is_some_and(|c| c.is_ascii_digit()) {
+            pos += 1;
+        }
+        *value = std::str::from_utf8(&data[start..pos]).ok().and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid("bad PPM header"))?;
+    }
+    let [width, height, max] = header;
+    if max == 0 || max > 65535 {
+        return Err(invalid("bad PPM maxval"));
+    }
+    let count = width as usize * height as usize * 3;
+
+    let values: Vec<u32> = if data.starts_with(b"P3") {
+        let text = std::str::from_utf8(&data[pos..]).map_err(|_| invalid("bad P3 data"))?;
+        text.split_whitespace().take(count).map(|v| v.parse().map_err(|_| invalid("bad P3 value")))
+            .collect::<io::Result<_>>()?
+    } else {
+        let body = &data[(pos + 1).min(data.len())..];
+        if max < 256 {
+            body.iter().take(count).map(|&b| b as u32).collect()
+        } else {
+            body.chunks_exact(2).take(count).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32).collect()
+        }
+    };
+    if values.len() < count {
+        return Err(invalid("PPM data is truncated"));
+    }
+    let pixels = values.chunks_exact(3)
+        .map(|c| Vec3::new(dequantize(c[0], max), dequantize(c[1], max), dequantize(c[2], max)))
+        .collect();
+    Ok(Framebuffer::from_pixels(width as usize, height as usize, pixels))
+}
+
+fn read_png(data: &[u8]) -> io::Result<Framebuffer> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let channels = info.color_type.samples();
+    let pixels = buf[..info.buffer_size()].chunks_exact(channels).map(|c| {
+        let (r, g, b) = if channels < 3 { (c[0], c[0], c[0]) } else { (c[0], c[1], c[2]) };
+        Vec3::new(dequantize(r as u32, 255), dequantize(g as u32, 255), dequantize(b as u32, 255))
+    }).collect();
+    Ok(Framebuffer::from_pixels(info.width as usize, info.height as usize, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> Framebuffer {
+        let mut image = Framebuffer::new(2, 1);
+        image.set(0, 0, Vec3::new(1.0, 0.25, 0.0));
+        image.set(1, 0, Vec3::new(4.0, 0.5, 2.0));
+        image
+    }
+
+    #[test]
+    fn p6_header_and_payload() {
+        let mut out = Vec::new();
+        PpmWriter::default().write(&image(), &mut out).unwrap();
+        assert_eq!(&out[..11], b"P6\n2 1\n255\n");
+        assert_eq!(&out[11..], &[255, 137, 0, 255, 188, 255]);
+    }
+
+    #[test]
+    fn rgbe_round_trips_within_mantissa_precision() {
+        let col = Vec3::new(4.0, 0.5, 2.0);
+        let [r, g, b, e] = rgbe(col);
+        let scale = 2f32.powi(e as i32 - 136);
+        assert_eq!(((r as f32 + 0.5) * scale).round(), 4.0);
+        assert!(((g as f32 + 0.5) * scale - 0.5).abs() < 0.02);
+        assert!(((b as f32 + 0.5) * scale - 2.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        assert_eq!(ImageFormat::from_path(Path::new("out.PNG")).unwrap(), ImageFormat::Png);
+        assert_eq!(ImageFormat::from_path(Path::new("a/b.pfm")).unwrap(), ImageFormat::Pfm);
+        assert!(ImageFormat::from_path(Path::new("out.jpg")).is_err());
+    }
+
+    #[test]
+    fn ascii_ppm_is_chosen_by_name() {
+        assert_eq!(ImageFormat::named("ppm"), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::named("ppm-ascii"), Some(ImageFormat::PpmAscii));
+        assert_eq!(ImageFormat::named("jpg"), None);
+        let mut out = Vec::new();
+        ImageFormat::named("ppm-ascii").unwrap().writer(OutputSettings::default()).write(&image(), &mut out).unwrap();
+        assert!(out.starts_with(b"P3\n2 1\n255\n"), "{:?}", String::from_utf8_lossy(&out));
+    }
+
+    #[test]
+    fn written_images_read_back() {
+        for format in [ImageFormat::PpmAscii, ImageFormat::Ppm, ImageFormat::Png].iter() {
+            let mut out = Vec::new();
+            format.writer(OutputSettings::default()).write(&image(), &mut out).unwrap();
+            let read = read_image(&out).unwrap();
+            assert_eq!((read.width(), read.height()), (2, 1));
+            assert_eq!(read.to_rgb8(), image().to_rgb8(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn write_errors_propagate() {
+        assert!(save(&image(), "/nonexistent-dir/out.ppm").is_err());
+    }
+}
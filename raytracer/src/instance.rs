@@ -0,0 +1,62 @@
+use crate::aabb::Aabb;
+use crate::hitable::{HitRecord, Hitable};
+use crate::ray::Ray;
+use crate::transform::Transform;
+use std::sync::Arc;
+
+/// Places a shared object in the scene through a `Transform`. Rays are
+/// moved into the object's own space, and hits are moved back out, so one
+/// mesh can appear many times without being copied.
+pub struct TransformedInstance {
+    object: Arc<dyn Hitable>,
+    transform: Transform,
+}
+
+impl TransformedInstance {
+    pub fn new(object: Arc<dyn Hitable>, transform: Transform) -> Self {
+        TransformedInstance { object, transform }
+    }
+}
+
+impl Hitable for TransformedInstance {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // The direction is not renormalized, so `t` means the same distance
+        // along the ray in both spaces.
+        let inverse = self.transform.inverse();
+        let local = Ray { origin: inverse.point(r.origin), direction: inverse.vector(r.direction), ..*r };
+        self.object.hit(&local, t_min, t_max).map(|rec| HitRecord {
+            p: self.transform.point(rec.p),
+            normal: self.transform.normal(rec.normal).normalize(),
+            ..rec
+        })
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.object.bounding_box(time0, time1).map(|bbox| self.transform.bounding_box(&bbox))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::Sphere;
+    use crate::material::Lambertian;
+    use crate::myvec::Vec3;
+
+    #[test]
+    fn stretched_sphere_is_hit_in_world_space() {
+        let sphere: Arc<dyn Hitable> = Arc::new(Sphere::new(Vec3::default(), 1.0,
+            Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))));
+        let transform = Transform::translate(Vec3::new(0., 0., -10.)) * Transform::scale(Vec3::new(3., 1., 1.));
+        let instance = TransformedInstance::new(Arc::clone(&sphere), transform);
+
+        let r = Ray::new(Vec3::new(5., 0., -10.), Vec3::new(-1., 0., 0.));
+        let rec = instance.hit(&r, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-5);
+        assert!((rec.p - Vec3::new(3., 0., -10.)).length() < 1e-5);
+        assert!((rec.normal - Vec3::new(1., 0., 0.)).length() < 1e-5);
+
+        let bbox = instance.bounding_box(0.0, 0.0).unwrap();
+        assert_eq!((bbox.min.x, bbox.max.x, bbox.min.z), (-3., 3., -11.));
+    }
+}
@@ -0,0 +1,300 @@
+use crate::hitable::{HitRecord, Hitable, HitableList};
+use crate::material::Lobe;
+use crate::pdf::{HitablePdf, Pdf};
+use crate::myvec::Vec3;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+
+/// What a ray sees when it leaves the scene without hitting anything.
+#[derive(Debug, Clone, Copy)]
+pub enum Background {
+    /// The white-to-blue gradient of the book.
+    Sky,
+    /// A constant color; black leaves the lights as the only source.
+    Solid(Vec3),
+}
+
+impl Background {
+    pub fn color(&self, r: &Ray) -> Vec3 {
+        match *self {
+            Background::Sky => {
+                let unit_direction = r.direction.normalize();
+                let t = 0.5 * (unit_direction.y + 1.0);
+                Vec3::new(1.0, 1.0, 1.0) * (1.0 - t) + Vec3::new(0.5, 0.7, 1.0) * t
+            }
+            Background::Solid(c) => c,
+        }
+    }
+}
+
+/// Homogeneous fog filling the whole scene. Light travelling a distance `d`
+/// through it is dimmed by `exp(-density * d)` and the rest is made up by
+/// the fog `color`, so rays that miss everything see only the fog.
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub color: Vec3,
+    pub density: f32,
+}
+
+impl Fog {
+    /// `radiance` as seen through `distance` units of fog.
+    pub fn apply(&self, radiance: Vec3, distance: f32) -> Vec3 {
+        if self.density <= 0.0 {
+            return radiance;
+        }
+        let transmittance = self.transmittance(distance);
+        radiance * transmittance + self.color * (1.0 - transmittance)
+    }
+
+    /// Fraction of light that crosses `distance` units of fog.
+    pub fn transmittance(&self, distance: f32) -> f32 {
+        if self.density <= 0.0 {
+            return 1.0;
+        }
+        (-self.density * distance).exp()
+    }
+}
+
+/// Traces paths through the scene and adds up the light along them.
+#[derive(Debug, Clone, Copy)]
+pub struct Integrator {
+    /// Bounces after which a path is cut off, whatever it still carries.
+    pub max_depth: usize,
+    /// Bounces after which Russian roulette may end a path early. A path
+    /// survives with a probability that follows how much light it can still
+    /// carry, and survivors are scaled up so that the image stays unbiased.
+    pub roulette_depth: usize,
+    pub background: Background,
+    pub fog: Option<Fog>,
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Integrator { max_depth: 50, roulette_depth: 5, background: Background::Sky, fog: None }
+    }
+}
+
+/// Veach's power heuristic: the weight of a sample drawn with density `a`
+/// when the same path could also have been drawn with density `b`.
+fn power_heuristic(a: f32, b: f32) -> f32 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 > 0.0 {
+        a2 / (a2 + b2)
+    } else {
+        0.0
+    }
+}
+
+impl Integrator {
+    /// Radiance arriving along `r`. At every non-specular hit one of the
+    /// `lights` is also sampled directly, and the two estimates are combined
+    /// by multiple importance sampling; with no lights only the scattered
+    /// rays find light.
+    pub fn color(&self, r: &Ray, world: &dyn Hitable, lights: &HitableList, sampler: &mut dyn Sampler) -> Vec3 {
+        let sample_lights = lights.length() > 0;
+        let mut radiance = Vec3::default();
+        // Fraction of the light found further along that reaches the camera.
+        let mut throughput = Vec3::new(1., 1., 1.);
+        let mut ray = *r;
+        // Density with which the last hit chose `ray`, if that hit also
+        // sampled the lights; light found next is then weighted against the
+        // light sample.
+        let mut bsdf_pdf = None;
+
+        for depth in 0.. {
+            ray = ray.with_free_path(sampler);
+            let rec = match world.hit(&ray, 0.001, f32::MAX) {
+                Some(rec) => rec,
+                None => {
+                    let background = self.background.color(&ray);
+                    radiance += throughput * self.fog.map_or(background, |fog| fog.apply(background, f32::INFINITY));
+                    break;
+                }
+            };
+            if let Some(fog) = self.fog {
+                let transmittance = fog.transmittance(rec.t * ray.direction.length());
+                radiance += throughput * fog.color * (1.0 - transmittance);
+                throughput *= transmittance;
+            }
+            let mut emitted = rec.material.emitted(rec.u, rec.v, rec.p);
+            if let Some(bsdf_pdf) = bsdf_pdf {
+                emitted *= power_heuristic(bsdf_pdf, lights.pdf_value(ray.origin, ray.direction));
+            }
+            radiance += throughput * emitted;
+            if depth >= self.max_depth {
+                break;
+            }
+            let srec = match rec.material.scatter(&ray, &rec, sampler) {
+                Some(srec) => srec,
+                None => break,
+            };
+
+            match srec.lobe {
+                Lobe::Specular(scattered) => {
+                    throughput *= srec.attenuation;
+                    ray = scattered;
+                    bsdf_pdf = None;
+                }
+                Lobe::Sampled(pdf) => {
+                    if sample_lights {
+                        let direct = self.sample_light(&ray, &rec, srec.attenuation, pdf.as_ref(), world, lights, sampler);
+                        radiance += throughput * direct;
+                    }
+                    let scattered = Ray::with_time(rec.p, pdf.generate(sampler), ray.time);
+                    let pdf_value = pdf.value(scattered.direction);
+                    let bsdf = rec.material.scattering_pdf(&ray, &rec, &scattered);
+                    if pdf_value <= 0.0 || bsdf <= 0.0 {
+                        break;
+                    }
+                    throughput *= srec.attenuation * (bsdf / pdf_value);
+                    ray = scattered;
+                    bsdf_pdf = if sample_lights { Some(pdf_value) } else { None };
+                }
+            }
+
+            if depth + 1 >= self.roulette_depth {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).min(1.0);
+                if sampler.next_f32() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+        radiance
+    }
+
+    /// Light arriving at `rec` straight from a random point on the `lights`,
+    /// through a shadow ray, weighted against finding it through `pdf`.
+    /// Whatever the shadow ray hits first supplies the light, so blockers
+    /// that do not glow leave it dark.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_light(&self, r: &Ray, rec: &HitRecord, attenuation: Vec3, pdf: &dyn Pdf, world: &dyn Hitable,
+                    lights: &HitableList, sampler: &mut dyn Sampler) -> Vec3 {
+        let light = HitablePdf::new(lights, rec.p);
+        let direction = light.generate(sampler);
+        let light_pdf = light.value(direction);
+        if light_pdf <= 0.0 {
+            return Vec3::default();
+        }
+        let shadow = Ray::with_time(rec.p, direction, r.time).with_free_path(sampler);
+        let bsdf = rec.material.scattering_pdf(r, rec, &shadow);
+        if bsdf <= 0.0 {
+            return Vec3::default();
+        }
+        match world.hit(&shadow, 0.001, f32::MAX) {
+            Some(hit) => {
+                // Only the light itself is weighted here; the fog color along
+                // the way is left to the scattered ray.
+                let mut emitted = hit.material.emitted(hit.u, hit.v, hit.p);
+                if let Some(fog) = self.fog {
+                    emitted *= fog.transmittance(hit.t * shadow.direction.length());
+                }
+                let weight = power_heuristic(light_pdf, pdf.value(direction));
+                emitted * attenuation * (bsdf * weight / light_pdf)
+            }
+            None => Vec3::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::Sphere;
+    use crate::material::{DiffuseLight, Material, ScatterRecord};
+    use crate::pdf::CosinePdf;
+    use crate::sampler::Pcg32;
+    use std::sync::Arc;
+
+    #[test]
+    fn light_is_seen_against_black_background() {
+        let mut world = HitableList::default();
+        world.add(Box::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5,
+            Arc::new(DiffuseLight::new(Vec3::new(4., 4., 4.))))));
+        let integrator = Integrator { background: Background::Solid(Vec3::default()), ..Integrator::default() };
+        let mut sampler = Pcg32::new(0, 0);
+
+        let hit = integrator.color(&Ray::new(Vec3::default(), Vec3::new(0., 0., -1.)), &world, &HitableList::default(), &mut sampler);
+        assert_eq!((hit.x, hit.y, hit.z), (4., 4., 4.));
+        let miss = integrator.color(&Ray::new(Vec3::default(), Vec3::new(0., 1., 0.)), &world, &HitableList::default(), &mut sampler);
+        assert_eq!((miss.x, miss.y, miss.z), (0., 0., 0.));
+    }
+
+    #[test]
+    fn fog_hides_what_is_far_away() {
+        let fog = Fog { color: Vec3::new(0.5, 0.5, 0.5), density: 0.1 };
+        let light = Vec3::new(4., 4., 4.);
+        assert_eq!(fog.apply(light, 0.0).x, 4.0);
+        let near = fog.apply(light, 1.0).x;
+        let far = fog.apply(light, 20.0).x;
+        assert!(near > far && far > 0.5);
+        assert_eq!(fog.apply(light, f32::INFINITY).x, 0.5);
+
+        let mut world = HitableList::default();
+        world.add(Box::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5,
+            Arc::new(DiffuseLight::new(light)))));
+        let integrator = Integrator { fog: Some(fog), ..Integrator::default() };
+        let miss = integrator.color(&Ray::new(Vec3::default(), Vec3::new(0., 1., 0.)), &world, &HitableList::default(), &mut Pcg32::new(0, 0));
+        assert_eq!((miss.x, miss.y, miss.z), (0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn light_sampling_converges_to_the_same_image() {
+        use crate::material::Lambertian;
+        use crate::rect::XZRect;
+        let lamp = Arc::new(Sphere::new(Vec3::new(0., 2., 0.), 0.5, Arc::new(DiffuseLight::new(Vec3::new(4., 4., 4.)))));
+        let mut world = HitableList::default();
+        world.add(Box::new(XZRect::new(-50., 50., -50., 50., 0., Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))))));
+        world.add(Box::new(Arc::clone(&lamp)));
+        let mut lights = HitableList::default();
+        lights.add(Box::new(lamp));
+        let integrator = Integrator { background: Background::Solid(Vec3::default()), ..Integrator::default() };
+
+        let r = Ray::new(Vec3::new(1., 1., 0.), Vec3::new(0., -1., 0.));
+        let mean = |lights: &HitableList| {
+            let mut sampler = Pcg32::new(5, 0);
+            let n = 100_000;
+            (0..n).map(|_| integrator.color(&r, &world, lights, &mut sampler).x).sum::<f32>() / n as f32
+        };
+        let scattered_only = mean(&HitableList::default());
+        let sampled = mean(&lights);
+        assert!((sampled - scattered_only).abs() < 0.03 * scattered_only, "{} vs {}", sampled, scattered_only);
+    }
+
+    /// Glows with 1 and reflects half of what arrives, diffusely.
+    struct GlowingWall;
+
+    impl Material for GlowingWall {
+        fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+            let lobe = Lobe::Sampled(Box::new(CosinePdf::new(rec.normal)));
+            Some(ScatterRecord { attenuation: Vec3::new(0.5, 0.5, 0.5), lobe })
+        }
+
+        fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+            rec.normal.dot(scattered.direction.normalize()).max(0.0) / std::f32::consts::PI
+        }
+
+        fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
+            Vec3::new(1., 1., 1.)
+        }
+    }
+
+    #[test]
+    fn russian_roulette_is_unbiased() {
+        // Inside a closed glowing room every bounce adds half the light of
+        // the one before, so the radiance is 1 + 1/2 + 1/4 + ... = 2.
+        let mut world = HitableList::default();
+        world.add(Box::new(Sphere::new(Vec3::default(), -1.0, Arc::new(GlowingWall))));
+        let r = Ray::new(Vec3::default(), Vec3::new(0., 0., -1.));
+        let mut sampler = Pcg32::new(6, 0);
+
+        let exact = Integrator { max_depth: 100, roulette_depth: 1000, ..Integrator::default() };
+        let full = exact.color(&r, &world, &HitableList::default(), &mut sampler).x;
+        assert!((full - 2.0).abs() < 1e-4, "{}", full);
+
+        let roulette = Integrator { max_depth: 100, roulette_depth: 1, ..Integrator::default() };
+        let n = 100_000;
+        let mean = (0..n).map(|_| roulette.color(&r, &world, &HitableList::default(), &mut sampler).x).sum::<f32>() / n as f32;
+        assert!((mean - 2.0).abs() < 0.02, "{}", mean);
+    }
+}
@@ -0,0 +1,62 @@
+//! Shared core of the "Ray Tracing in One Weekend" chapters.
+//!
+//! Every chapter binary builds on the types exported here instead of
+//! carrying its own copy of them.
+
+pub mod myvec;
+pub mod transform;
+pub mod ray;
+pub mod hitable;
+pub mod rect;
+pub mod mesh;
+pub mod obj;
+pub mod instance;
+pub mod medium;
+pub mod aabb;
+pub mod bvh;
+pub mod camera;
+pub mod material;
+pub mod pdf;
+pub mod texture;
+pub mod noise;
+pub mod integrator;
+pub mod sampler;
+pub mod qmc;
+pub mod film;
+pub mod image;
+pub mod tonemap;
+pub mod render;
+pub mod checkpoint;
+pub mod aov;
+pub mod denoise;
+pub mod scene;
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub use myvec::Vec3;
+pub use transform::Transform;
+pub use ray::Ray;
+pub use hitable::{HitRecord, Hitable, HitableList, Motion, MovingSphere, Sphere, Tagged};
+pub use rect::{Cuboid, FlipNormals, XYRect, XZRect, YZRect};
+pub use mesh::{Triangle, TriangleMesh, Vertex};
+pub use obj::load_obj;
+pub use instance::TransformedInstance;
+pub use medium::ConstantMedium;
+pub use aabb::Aabb;
+pub use bvh::BvhNode;
+pub use camera::{Camera, CameraSettings};
+pub use material::{Material, Lambertian, Metal, Dielectric, DiffuseLight, Isotropic, Lobe, ScatterRecord};
+pub use pdf::{CosinePdf, GgxPdf, HitablePdf, MixturePdf, Pdf, SpherePdf};
+pub use texture::{CheckerTexture, ConstantTexture, ImageTexture, Texture, WrapMode};
+pub use noise::{NoiseTexture, Pattern, Perlin, Worley};
+pub use integrator::{Background, Fog, Integrator};
+pub use sampler::{IndependentSampler, Pcg32, Sampler, SamplerKind};
+pub use qmc::{BlueNoiseSampler, HaltonSampler, SobolSampler, StratifiedSampler};
+pub use image::{load_image, save, save_as, save_with, Framebuffer, ImageFormat, ImageWriter};
+pub use tonemap::{OutputSettings, ToneMap};
+pub use film::{Film, Filter};
+pub use render::{render, render_film, render_pass, render_with_progress, AdaptiveSettings, RenderSettings};
+pub use checkpoint::Checkpoint;
+pub use aov::{render_aovs, Aov, Aovs};
+pub use denoise::{denoise, DenoiseSettings};
+pub use scene::{load_scene, Scene, SceneError};
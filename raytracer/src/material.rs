@@ -0,0 +1,321 @@
+use crate::myvec::Vec3;
+use crate::ray::Ray;
+use crate::hitable::HitRecord;
+use crate::pdf::{ggx_d, smith_g1, CosinePdf, GgxPdf, Pdf, SpherePdf};
+use crate::sampler::Sampler;
+use crate::texture::{ConstantTexture, Texture};
+use std::sync::Arc;
+
+/// Where a scattered ray goes.
+pub enum Lobe {
+    /// Exactly along this ray, as off a mirror or through glass.
+    Specular(Ray),
+    /// In a direction drawn from this density.
+    Sampled(Box<dyn Pdf>),
+}
+
+/// How a ray continues after hitting a material.
+pub struct ScatterRecord {
+    /// Color the scattered light is tinted with. For a sampled lobe it is
+    /// scaled by `scattering_pdf` over the density of the chosen direction.
+    pub attenuation: Vec3,
+    pub lobe: Lobe,
+}
+
+impl ScatterRecord {
+    pub fn is_specular(&self) -> bool {
+        matches!(self.lobe, Lobe::Specular(_))
+    }
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<ScatterRecord>;
+
+    /// The BSDF times the cosine term towards `scattered`, divided by the
+    /// attenuation. Only used for sampled lobes.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
+        0.0
+    }
+
+    /// Light given off at surface coordinates `(u, v)` and point `p`.
+    fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
+        Vec3::default()
+    }
+
+    /// Base color at the hit, for feature images: the fraction of light
+    /// the surface passes on, whatever the direction. Black if it only
+    /// glows.
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::default()
+    }
+}
+
+pub struct Lambertian {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Vec3) -> Self {
+        Lambertian::textured(Arc::new(ConstantTexture::new(albedo)))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Lambertian { albedo }
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+        let attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        Some(ScatterRecord { attenuation, lobe: Lobe::Sampled(Box::new(CosinePdf::new(rec.normal))) })
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        let cosine = rec.normal.dot(scattered.direction.normalize());
+        cosine.max(0.0) / std::f32::consts::PI
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p)
+    }
+}
+
+
+fn reflect(v: Vec3, n: Vec3) -> Vec3 {
+    v - n * (v.dot(n) * 2.0)
+}
+
+fn refract(v: Vec3, n: Vec3, ni_over_nt: f32) -> Option<Vec3> {
+    let uv = v.normalize();
+    let dt = uv.dot(n);
+    let discriminant = 1.0 - ni_over_nt*ni_over_nt*(1.0 - dt*dt);
+    if discriminant > 0.0 {
+        let refracted = (uv - n*dt)*ni_over_nt - n*discriminant.sqrt();
+        Some(refracted)
+    } else {
+        None
+    }
+}
+
+/// `fuzz` is the GGX roughness; at 0 the metal is a perfect mirror. The
+/// albedo stands in for the Fresnel term at every angle.
+pub struct Metal {
+    albedo: Arc<dyn Texture>,
+    fuzz: f32,
+}
+
+impl Metal {
+    pub fn new(albedo: Vec3, fuzz: f32) -> Self {
+        Metal::textured(Arc::new(ConstantTexture::new(albedo)), fuzz)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, mut fuzz: f32) -> Self {
+        if fuzz >= 1.0 {
+            fuzz = 1.0;
+        }
+        Metal { albedo, fuzz }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+        let attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        let wo = -r_in.direction.normalize();
+        let lobe = if self.fuzz > 0.0 {
+            Lobe::Sampled(Box::new(GgxPdf::new(rec.normal, wo, self.fuzz)))
+        } else {
+            Lobe::Specular(Ray::with_time(rec.p, reflect(-wo, rec.normal), r_in.time))
+        };
+        Some(ScatterRecord { attenuation, lobe })
+    }
+
+    /// Microfacet reflection, `D G / (4 cos(wo))`, with Smith masking and
+    /// shadowing. Rays the facets would send below the surface get 0.
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        let wo = -r_in.direction.normalize();
+        let wi = scattered.direction.normalize();
+        let (cos_o, cos_i) = (wo.dot(rec.normal), wi.dot(rec.normal));
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalize();
+        ggx_d(self.fuzz, h.dot(rec.normal)) * smith_g1(self.fuzz, cos_o) * smith_g1(self.fuzz, cos_i) / (4.0 * cos_o)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p)
+    }
+}
+
+pub struct Dielectric {
+    ref_idx: f32,
+}
+
+impl Dielectric {
+    pub fn new(ref_idx: f32) -> Self {
+        Dielectric { ref_idx }
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+        let reflected = reflect(r_in.direction.normalize(), rec.normal);
+        let attenuation = Vec3::new(1.0, 1.0, 1.0);
+        let (outward_normal, ni_over_nt, cosine) =
+            if r_in.direction.dot(rec.normal) > 0.0 {
+                let cosine = self.ref_idx * r_in.direction.dot(rec.normal) / r_in.direction.length();
+                (-rec.normal, self.ref_idx, cosine)
+            } else {
+                let cosine = -r_in.direction.dot(rec.normal)/r_in.direction.length();
+                (rec.normal, 1.0 / self.ref_idx, cosine)
+            };
+        
+        match refract(r_in.direction, outward_normal, ni_over_nt) {
+            Some(refracted) => {
+                let reflect_prob = schlick(cosine, self.ref_idx);
+                let random = sampler.next_f32();
+                let scattered =
+                    if random < reflect_prob {
+                        Ray::with_time(rec.p, reflected, r_in.time)
+                    } else {
+                        Ray::with_time(rec.p, refracted, r_in.time)
+                    };
+                Some(ScatterRecord { attenuation, lobe: Lobe::Specular(scattered) })
+            }
+            None => {
+                    let scattered = Ray::with_time(rec.p, reflected, r_in.time);
+                    Some(ScatterRecord { attenuation, lobe: Lobe::Specular(scattered) })
+                }
+        }
+    }    
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::new(1.0, 1.0, 1.0)
+    }
+}
+
+/// Glows with a constant color and scatters nothing.
+pub struct DiffuseLight {
+    emit: Vec3,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vec3) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
+        self.emit
+    }
+}
+
+/// Phase function of a participating medium: scatters equally in every
+/// direction, tinted by `albedo`.
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Arc<dyn Texture>) -> Self {
+        Isotropic { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+        let attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        Some(ScatterRecord { attenuation, lobe: Lobe::Sampled(Box::new(SpherePdf)) })
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
+        1.0 / (4.0 * std::f32::consts::PI)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p)
+    }
+}
+
+fn schlick(cosine: f32, ref_idx: f32) -> f32 {
+    let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
+    let r0 = r0 * r0;
+    r0 + (1.0 - r0)*(1.0 - cosine).powf(5.0)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    fn record(material: Arc<dyn Material>) -> HitRecord {
+        HitRecord { t: 1.0, p: Vec3::new(0., 0., 0.), normal: Vec3::new(0., 1., 0.), u: 0.0, v: 0.0, material, object_id: 0 }
+    }
+
+    fn specular(srec: ScatterRecord) -> Ray {
+        match srec.lobe {
+            Lobe::Specular(ray) => ray,
+            Lobe::Sampled(_) => panic!("expected a specular lobe"),
+        }
+    }
+
+    #[test]
+    fn scatter_is_reproducible_for_a_seed() {
+        let rec = record(Arc::new(Dielectric::new(1.5)));
+        let r_in = Ray::new(Vec3::new(-1., 1., 0.), Vec3::new(1., -1., 0.));
+        let directions: Vec<_> = (0..2).map(|_| {
+            let mut sampler = Pcg32::new(3, 0);
+            (0..16).map(|_| {
+                let scattered = specular(rec.material.scatter(&r_in, &rec, &mut sampler).unwrap());
+                (scattered.direction.x, scattered.direction.y, scattered.direction.z)
+            }).collect::<Vec<_>>()
+        }).collect();
+        assert_eq!(directions[0], directions[1]);
+    }
+
+    #[test]
+    fn polished_metal_reflects_mirror_direction() {
+        let rec = record(Arc::new(Metal::new(Vec3::new(1., 1., 1.), 0.0)));
+        let r_in = Ray::new(Vec3::new(-1., 1., 0.), Vec3::new(1., -1., 0.));
+        let scattered = specular(rec.material.scatter(&r_in, &rec, &mut Pcg32::new(0, 0)).unwrap());
+        let d = scattered.direction.normalize();
+        let expected = Vec3::new(1., 1., 0.).normalize();
+        assert!((d - expected).length() < 1e-6);
+    }
+
+    /// Average weight `attenuation * scattering_pdf / pdf` of the rays a
+    /// white material scatters for `r_in`: the fraction of light it keeps.
+    fn directional_albedo(material: Arc<dyn Material>, r_in: &Ray) -> f32 {
+        let rec = record(material);
+        let mut sampler = Pcg32::new(4, 0);
+        let n = 50_000;
+        let total: f32 = (0..n).map(|_| {
+            let srec = rec.material.scatter(r_in, &rec, &mut sampler).unwrap();
+            let pdf = match srec.lobe {
+                Lobe::Sampled(pdf) => pdf,
+                Lobe::Specular(_) => return srec.attenuation.x,
+            };
+            let scattered = Ray::new(rec.p, pdf.generate(&mut sampler));
+            let value = pdf.value(scattered.direction);
+            if value > 0.0 { srec.attenuation.x * rec.material.scattering_pdf(r_in, &rec, &scattered) / value } else { 0.0 }
+        }).sum();
+        total / n as f32
+    }
+
+    #[test]
+    fn sampled_lobes_keep_at_most_all_the_light() {
+        let white = Vec3::new(1., 1., 1.);
+        let head_on = Ray::new(Vec3::new(0., 1., 0.), Vec3::new(0., -1., 0.));
+        let grazing = Ray::new(Vec3::new(-1., 0.2, 0.), Vec3::new(1., -0.2, 0.));
+        assert!((directional_albedo(Arc::new(Lambertian::new(white)), &grazing) - 1.0).abs() < 1e-3);
+        // Rough metal loses some light to masking, more so at grazing angles.
+        let smooth = directional_albedo(Arc::new(Metal::new(white, 0.1)), &head_on);
+        let rough = directional_albedo(Arc::new(Metal::new(white, 0.8)), &grazing);
+        assert!(smooth > 0.97 && smooth <= 1.01, "{}", smooth);
+        assert!(rough > 0.3 && rough < smooth, "{}", rough);
+    }
+}
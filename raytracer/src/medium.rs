@@ -0,0 +1,117 @@
+use crate::aabb::Aabb;
+use crate::hitable::{HitRecord, Hitable};
+use crate::material::{Isotropic, Material};
+use crate::myvec::Vec3;
+use crate::ray::Ray;
+use crate::texture::{ConstantTexture, Texture};
+use std::sync::Arc;
+
+/// Smoke or fog of constant density filling a closed `boundary`. A ray
+/// inside it travels an exponentially distributed distance before it
+/// scatters in a random direction: its `optical_depth` divided by the
+/// density.
+///
+/// Every medium a ray crosses measures the same optical depth from where
+/// the ray enters it, so the integrator draws a fresh one for each ray
+/// segment. Rays that cross one medium and then reach another within the
+/// same segment scatter in the second one less often than they should.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hitable>,
+    density: f32,
+    phase: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Box<dyn Hitable>, density: f32, albedo: Vec3) -> Self {
+        ConstantMedium::textured(boundary, density, Arc::new(ConstantTexture::new(albedo)))
+    }
+
+    /// Panics unless `density` is finite and positive.
+    pub fn textured(boundary: Box<dyn Hitable>, density: f32, albedo: Arc<dyn Texture>) -> Self {
+        assert!(density.is_finite() && density > 0.0, "medium density must be finite and positive, not {}", density);
+        ConstantMedium { boundary, density, phase: Arc::new(Isotropic::new(albedo)) }
+    }
+}
+
+impl Hitable for ConstantMedium {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Find where the whole line enters and leaves the boundary, so rays
+        // starting inside the medium work too.
+        let enter = self.boundary.hit(r, f32::MIN, f32::MAX)?;
+        let exit = self.boundary.hit(r, enter.t + 0.0001, f32::MAX)?;
+        let t0 = enter.t.max(t_min).max(0.0);
+        let t1 = exit.t.min(t_max);
+        if t0 >= t1 {
+            return None;
+        }
+        let length = r.direction.length();
+        let distance = r.optical_depth / self.density;
+        if distance > (t1 - t0) * length {
+            return None;
+        }
+        let t = t0 + distance / length;
+        Some(HitRecord {
+            t,
+            p: r.point_at_paramter(t),
+            // Isotropic scattering ignores the normal.
+            normal: Vec3::new(1., 0., 0.),
+            u: 0.0,
+            v: 0.0,
+            material: Arc::clone(&self.phase),
+            object_id: 0,
+        })
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.boundary.bounding_box(time0, time1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::Sphere;
+    use crate::material::Lambertian;
+    use crate::sampler::Pcg32;
+
+    fn fog(density: f32) -> ConstantMedium {
+        let boundary = Sphere::new(Vec3::default(), 1.0, Arc::new(Lambertian::new(Vec3::default())));
+        ConstantMedium::new(Box::new(boundary), density, Vec3::new(1., 1., 1.))
+    }
+
+    #[test]
+    fn transmittance_follows_beer_lambert() {
+        // A ray through the centre crosses 2 units of medium.
+        let medium = fog(0.5);
+        let mut sampler = Pcg32::new(3, 0);
+        let n = 20_000;
+        let passed = (0..n).filter(|_| {
+            let r = Ray::new(Vec3::new(0., 0., 5.), Vec3::new(0., 0., -1.)).with_free_path(&mut sampler);
+            medium.hit(&r, 0.001, f32::MAX).is_none()
+        }).count();
+        let expected = (-0.5f32 * 2.0).exp();
+        assert!((passed as f32 / n as f32 - expected).abs() < 0.02);
+    }
+
+    #[test]
+    fn rays_starting_inside_scatter_inside() {
+        let medium = fog(100.0);
+        let r = Ray::new(Vec3::new(0., 0., 0.5), Vec3::new(0., 0., -1.)).with_free_path(&mut Pcg32::new(0, 0));
+        let rec = medium.hit(&r, 0.001, f32::MAX).unwrap();
+        assert!(rec.t > 0.0 && rec.t < 1.5);
+        assert_eq!(rec.p.z, 0.5 - rec.t);
+    }
+
+    #[test]
+    fn free_paths_come_from_the_sampler() {
+        let medium = fog(0.5);
+        let r = Ray::new(Vec3::new(0., 0., 5.), Vec3::new(0., 0., -1.));
+        assert!(medium.hit(&r, 0.001, f32::MAX).is_none());
+        let depth = |seed| {
+            let r = r.with_free_path(&mut Pcg32::new(seed, 0));
+            medium.hit(&r, 0.001, f32::MAX).map(|rec| rec.t)
+        };
+        assert_eq!(depth(1), depth(1));
+        assert!((0..8).any(|seed| depth(seed) != depth(seed + 8)));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::aabb::Aabb;
+use crate::hitable::{area_pdf, HitRecord, Hitable};
+use crate::material::Material;
+use crate::myvec::Vec3;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::transform::Transform;
+use std::sync::Arc;
+
+/// Vertex data shared by all the triangles of a mesh.
+#[derive(Debug, Default)]
+pub struct TriangleMesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<[f32; 2]>,
+}
+
+/// Corner of a triangle, as indices into the buffers of its mesh.
+#[derive(Debug, Clone, Copy)]
+pub struct Vertex {
+    pub position: usize,
+    pub normal: Option<usize>,
+    pub uv: Option<usize>,
+}
+
+impl Vertex {
+    pub fn new(position: usize) -> Self {
+        Vertex { position, normal: None, uv: None }
+    }
+}
+
+/// Triangle with Möller–Trumbore intersection. The front side is the one
+/// from which the corners appear counter-clockwise.
+///
+/// When all three corners have normals they are interpolated for smooth
+/// shading; otherwise the flat face normal is used.
+pub struct Triangle {
+    mesh: Arc<TriangleMesh>,
+    vertices: [Vertex; 3],
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Panics if a vertex refers past the end of a mesh buffer.
+    pub fn new(mesh: Arc<TriangleMesh>, vertices: [Vertex; 3], material: Arc<dyn Material>) -> Self {
+        for v in vertices.iter() {
+            assert!(v.position < mesh.positions.len(), "position index out of range");
+            assert!(v.normal.is_none_or(|n| n < mesh.normals.len()), "normal index out of range");
+            assert!(v.uv.is_none_or(|t| t < mesh.uvs.len()), "uv index out of range");
+        }
+        Triangle { mesh, vertices, material }
+    }
+
+    /// A lone triangle with its own three-vertex mesh.
+    pub fn from_points(a: Vec3, b: Vec3, c: Vec3, material: Arc<dyn Material>) -> Self {
+        let mesh = TriangleMesh { positions: vec![a, b, c], ..TriangleMesh::default() };
+        Triangle::new(Arc::new(mesh), [Vertex::new(0), Vertex::new(1), Vertex::new(2)], material)
+    }
+
+    /// A lone copy of this triangle with its corners moved by `transform`,
+    /// dropping normals and texture coordinates. Lights inside a
+    /// `TransformedInstance` are sampled through such copies.
+    pub fn transformed(&self, transform: &Transform) -> Triangle {
+        let [a, b, c] = [0, 1, 2].map(|i| transform.point(self.position(i)));
+        Triangle::from_points(a, b, c, Arc::clone(&self.material))
+    }
+
+    fn position(&self, i: usize) -> Vec3 {
+        self.mesh.positions[self.vertices[i].position]
+    }
+}
+
+impl Hitable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let p0 = self.position(0);
+        let e1 = self.position(1) - p0;
+        let e2 = self.position(2) - p0;
+        let pvec = r.direction.cross(e2);
+        let det = e1.dot(pvec);
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let tvec = r.origin - p0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qvec = tvec.cross(e1);
+        let b2 = r.direction.dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = e2.dot(qvec) * inv_det;
+        if !(t > t_min && t < t_max) {
+            return None;
+        }
+        let b0 = 1.0 - b1 - b2;
+
+        let v = &self.vertices;
+        let normal = match (v[0].normal, v[1].normal, v[2].normal) {
+            (Some(n0), Some(n1), Some(n2)) => {
+                let n = &self.mesh.normals;
+                (n[n0] * b0 + n[n1] * b1 + n[n2] * b2).normalize()
+            }
+            _ => e1.cross(e2).normalize(),
+        };
+        let (u, v) = match (v[0].uv, v[1].uv, v[2].uv) {
+            (Some(t0), Some(t1), Some(t2)) => {
+                let uv = &self.mesh.uvs;
+                (uv[t0][0] * b0 + uv[t1][0] * b1 + uv[t2][0] * b2,
+                 uv[t0][1] * b0 + uv[t1][1] * b1 + uv[t2][1] * b2)
+            }
+            _ => (b1, b2),
+        };
+        let p = r.point_at_paramter(t);
+        Some(HitRecord { t, p, normal, u, v, material: Arc::clone(&self.material), object_id: 0 })
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        let (a, b, c) = (self.position(0), self.position(1), self.position(2));
+        let mut min = Vec3::new(a.x.min(b.x).min(c.x), a.y.min(b.y).min(c.y), a.z.min(b.z).min(c.z));
+        let mut max = Vec3::new(a.x.max(b.x).max(c.x), a.y.max(b.y).max(c.y), a.z.max(b.z).max(c.z));
+        // Keep triangles lying in an axis plane from getting a flat box.
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+        min -= pad;
+        max += pad;
+        Some(Aabb::new(min, max))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        match self.hit(&Ray::new(origin, direction), 0.001, f32::MAX) {
+            Some(rec) => {
+                // Use the flat normal: the area is sampled on the real surface,
+                // not the interpolated one.
+                let p0 = self.position(0);
+                let normal = (self.position(1) - p0).cross(self.position(2) - p0);
+                area_pdf(direction, rec.t, normal, 0.5 * normal.length())
+            }
+            None => 0.0,
+        }
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        // Uniform barycentric coordinates (Shirley and Chiu).
+        let su = sampler.next_f32().sqrt();
+        let b1 = sampler.next_f32() * su;
+        let b2 = 1.0 - su;
+        let p0 = self.position(0);
+        let p = p0 + (self.position(1) - p0) * b1 + (self.position(2) - p0) * b2;
+        p - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn hit_reports_barycentric_uv_and_face_normal() {
+        let tri = Triangle::from_points(Vec3::new(0., 0., -1.), Vec3::new(1., 0., -1.), Vec3::new(0., 1., -1.), gray());
+        let rec = tri.hit(&Ray::new(Vec3::new(0.25, 0.5, 0.), Vec3::new(0., 0., -1.)), 0.001, f32::MAX).unwrap();
+        assert_eq!(rec.t, 1.0);
+        assert_eq!((rec.u, rec.v), (0.25, 0.5));
+        assert_eq!((rec.normal.x, rec.normal.y, rec.normal.z), (0., 0., 1.));
+        assert!(tri.hit(&Ray::new(Vec3::new(0.75, 0.5, 0.), Vec3::new(0., 0., -1.)), 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn vertex_normals_are_interpolated() {
+        let mesh = TriangleMesh {
+            positions: vec![Vec3::new(0., 0., 0.), Vec3::new(1., 0., 0.), Vec3::new(0., 1., 0.)],
+            normals: vec![Vec3::new(0., 0., 1.), Vec3::new(1., 0., 0.)],
+            uvs: Vec::new(),
+        };
+        let corner = |position, normal| Vertex { position, normal: Some(normal), uv: None };
+        let tri = Triangle::new(Arc::new(mesh), [corner(0, 0), corner(1, 1), corner(2, 0)], gray());
+        let rec = tri.hit(&Ray::new(Vec3::new(0.5, 0., 1.), Vec3::new(0., 0., -1.)), 0.001, f32::MAX).unwrap();
+        let expected = Vec3::new(1., 0., 1.).normalize();
+        assert!((rec.normal - expected).length() < 1e-6);
+    }
+}
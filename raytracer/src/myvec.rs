@@ -22,8 +22,7 @@ impl Vec3 {
     
     pub fn normalize(&self) -> Self {
         let len = self.length();
-        let value = self.clone() / len;
-        return value;        
+        *self / len
     }
 
     pub fn dot(&self, rhs: Vec3) -> f32 {
@@ -36,6 +35,29 @@ impl Vec3 {
         let z = self.x*rhs.y - self.y*rhs.x;
         Vec3::new(x,y,z)
     }
+
+    /// Two unit vectors that, together with this unit vector, make a
+    /// right-handed orthonormal basis (Duff et al., 2017).
+    pub fn basis(&self) -> (Vec3, Vec3) {
+        let sign = 1f32.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+        let u = Vec3::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x);
+        let v = Vec3::new(b, sign + self.y * self.y * a, -self.y);
+        (u, v)
+    }
+}
+
+impl ops::Index<usize> for Vec3 {
+    type Output = f32;
+    fn index(&self, axis: usize) -> &f32 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 axis out of range: {}", axis),
+        }
+    }
 }
 
 impl ops::Add<Vec3> for Vec3 {
@@ -133,4 +155,31 @@ impl DivAssign<f32> for Vec3 {
         self.y /= other;
         self.z /= other;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_gives_unit_length() {
+        let v = Vec3::new(3.0, -4.0, 12.0).normalize();
+        assert!((v.length() - 1.0).abs() < 1e-6);
+        assert!((v.x - 3.0 / 13.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cross_follows_right_hand_rule() {
+        let z = Vec3::new(1., 0., 0.).cross(Vec3::new(0., 1., 0.));
+        assert_eq!((z.x, z.y, z.z), (0., 0., 1.));
+    }
+
+    #[test]
+    fn basis_is_orthonormal() {
+        for &n in [Vec3::new(0., 0., 1.), Vec3::new(0., 0., -1.), Vec3::new(1., 2., -3.).normalize()].iter() {
+            let (u, v) = n.basis();
+            assert!((u.length() - 1.0).abs() < 1e-6 && (v.length() - 1.0).abs() < 1e-6);
+            assert!(u.dot(v).abs() < 1e-6 && u.dot(n).abs() < 1e-6 && v.dot(n).abs() < 1e-6);
+            assert!((u.cross(v) - n).length() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Procedural textures built on gradient (Perlin) noise and cellular
+//! (Worley) noise. Everything is derived from a seed, so the same seed gives
+//! the same pattern on every run and every thread.
+
+use crate::myvec::Vec3;
+use crate::sampler::{Pcg32, Sampler};
+use crate::texture::Texture;
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise after Ken Perlin, with random unit gradients on the
+/// integer lattice and Hermite-smoothed trilinear interpolation.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Pcg32::new(seed, 0);
+        let gradients = (0..POINT_COUNT).map(|_| loop {
+            let v = Vec3::new(rng.next_range(-1.0, 1.0), rng.next_range(-1.0, 1.0), rng.next_range(-1.0, 1.0));
+            let len = v.length();
+            if len > 0.01 && len <= 1.0 {
+                break v / len;
+            }
+        }).collect();
+        let perm = [permutation(&mut rng), permutation(&mut rng), permutation(&mut rng)];
+        Perlin { gradients, perm }
+    }
+
+    /// Noise value in `[-1, 1]`; exactly 0 on lattice points.
+    pub fn noise(&self, p: Vec3) -> f32 {
+        let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (u, v, w) = (p.x - fx, p.y - fy, p.z - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+        let (uu, vv, ww) = (smooth(u), smooth(v), smooth(w));
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.perm[0][lattice(i + di)] ^ self.perm[1][lattice(j + dj)] ^ self.perm[2][lattice(k + dk)];
+                    let (a, b, c) = (di as f32, dj as f32, dk as f32);
+                    let weight = Vec3::new(u - a, v - b, w - c);
+                    accum += (a * uu + (1.0 - a) * (1.0 - uu))
+                        * (b * vv + (1.0 - b) * (1.0 - vv))
+                        * (c * ww + (1.0 - c) * (1.0 - ww))
+                        * self.gradients[index].dot(weight);
+                }
+            }
+        }
+        accum
+    }
+
+    /// Sum of `octaves` layers of `|noise|`, each at twice the frequency
+    /// and half the weight of the one before.
+    pub fn turbulence(&self, p: Vec3, octaves: usize) -> f32 {
+        let mut accum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            accum += weight * self.noise(p).abs();
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        accum
+    }
+}
+
+fn permutation(rng: &mut Pcg32) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+    for i in (1..POINT_COUNT).rev() {
+        let target = rng.next_u32() as usize % (i + 1);
+        p.swap(i, target);
+    }
+    p
+}
+
+fn lattice(i: i64) -> usize {
+    i.rem_euclid(POINT_COUNT as i64) as usize
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// SplitMix64 finaliser, used to hash lattice cells.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Cellular noise after Steven Worley: one random feature point per unit
+/// cell.
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Worley { seed }
+    }
+
+    fn cell(&self, i: i64, j: i64, k: i64) -> Pcg32 {
+        let h = mix(mix(mix(self.seed ^ i as u64) ^ j as u64) ^ k as u64);
+        Pcg32::new(h, 0)
+    }
+
+    /// Distance from `p` to the nearest feature point, and a random value in
+    /// `[0, 1)` that is constant over that point's Voronoi cell.
+    pub fn nearest(&self, p: Vec3) -> (f32, f32) {
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        let mut best = (f32::MAX, 0.0);
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let (ci, cj, ck) = (i + di, j + dj, k + dk);
+                    let mut rng = self.cell(ci, cj, ck);
+                    let feature = Vec3::new(ci as f32 + rng.next_f32(), cj as f32 + rng.next_f32(), ck as f32 + rng.next_f32());
+                    let d = (feature - p).length();
+                    if d < best.0 {
+                        best = (d, rng.next_f32());
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+/// How a `NoiseTexture` turns noise into a blend factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Smooth Perlin noise.
+    Noise,
+    /// Fractal sum of noise octaves, giving a cloudy look.
+    Turbulence,
+    /// Sine stripes along `z` disturbed by turbulence.
+    Marble,
+    /// Rings around the `y` axis disturbed by turbulence.
+    Wood,
+    /// Distance to the nearest Worley feature point.
+    Worley,
+    /// Flat Voronoi cells with a random shade each.
+    Voronoi,
+}
+
+/// Blends from `low` to `high` by a noise pattern evaluated at `p * scale`.
+pub struct NoiseTexture {
+    pattern: Pattern,
+    perlin: Perlin,
+    worley: Worley,
+    scale: f32,
+    octaves: usize,
+    low: Vec3,
+    high: Vec3,
+}
+
+impl NoiseTexture {
+    pub fn new(pattern: Pattern, seed: u64, scale: f32, octaves: usize, low: Vec3, high: Vec3) -> Self {
+        NoiseTexture { pattern, perlin: Perlin::new(seed), worley: Worley::new(seed), scale, octaves, low, high }
+    }
+
+    /// Blend factor in `[0, 1]` at `p`.
+    pub fn factor(&self, p: Vec3) -> f32 {
+        let q = p * self.scale;
+        let t = match self.pattern {
+            Pattern::Noise => 0.5 * (1.0 + self.perlin.noise(q)),
+            Pattern::Turbulence => self.perlin.turbulence(q, self.octaves),
+            // As in the book, only the stripes follow `scale`; the veins keep
+            // the turbulence at its natural size.
+            Pattern::Marble => 0.5 * (1.0 + (q.z + 10.0 * self.perlin.turbulence(p, self.octaves)).sin()),
+            Pattern::Wood => {
+                let r = (q.x * q.x + q.z * q.z).sqrt() + 2.0 * self.perlin.turbulence(q, self.octaves);
+                r.fract()
+            }
+            Pattern::Worley => self.worley.nearest(q).0,
+            Pattern::Voronoi => self.worley.nearest(q).1,
+        };
+        t.clamp(0.0, 1.0)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f32, _v: f32, p: Vec3) -> Vec3 {
+        let t = self.factor(p);
+        self.low * (1.0 - t) + self.high * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<Vec3> {
+        let mut rng = Pcg32::new(9, 0);
+        (0..500).map(|_| Vec3::new(rng.next_range(-20., 20.), rng.next_range(-20., 20.), rng.next_range(-20., 20.))).collect()
+    }
+
+    #[test]
+    fn perlin_is_bounded_and_vanishes_on_the_lattice() {
+        let perlin = Perlin::new(5);
+        for p in points() {
+            assert!(perlin.noise(p).abs() <= 1.0);
+        }
+        assert_eq!(perlin.noise(Vec3::new(3., -7., 12.)), 0.0);
+    }
+
+    #[test]
+    fn patterns_depend_only_on_the_seed() {
+        let patterns = [Pattern::Noise, Pattern::Turbulence, Pattern::Marble, Pattern::Wood, Pattern::Worley, Pattern::Voronoi];
+        let white = Vec3::new(1., 1., 1.);
+        for &pattern in patterns.iter() {
+            let a = NoiseTexture::new(pattern, 3, 2.0, 7, Vec3::default(), white);
+            let b = NoiseTexture::new(pattern, 3, 2.0, 7, Vec3::default(), white);
+            let c = NoiseTexture::new(pattern, 4, 2.0, 7, Vec3::default(), white);
+            let mut differs = false;
+            for p in points() {
+                let t = a.factor(p);
+                assert!((0.0..=1.0).contains(&t));
+                assert_eq!(t.to_bits(), b.factor(p).to_bits(), "{:?}", pattern);
+                differs |= t != c.factor(p);
+            }
+            assert!(differs, "{:?} ignores the seed", pattern);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Wavefront OBJ meshes and their MTL material libraries.
+//!
+//! Only polygonal geometry is read: `v`, `vt`, `vn` and `f` (polygons are
+//! split into triangle fans), plus `mtllib` and `usemtl`. Groups, smoothing
+//! groups and other statements are skipped.
+//!
+//! MTL materials are mapped onto the materials of this crate:
+//!
+//! * a non-black `Ke` makes a `DiffuseLight`,
+//! * `illum` 4, 6, 7 or 9, or `d` below 1, makes a `Dielectric` with index `Ni`,
+//! * `illum` 3 or 5, or a black `Kd` with a non-black `Ks`, makes a `Metal`
+//!   colored by `Ks` whose fuzz follows the Phong exponent `Ns`,
+//! * anything else is `Lambertian` with albedo `Kd`.
+//!
+//! A `usemtl` naming a material no library defines falls back to the
+//! default material with a warning, as other renderers do.
+
+use crate::hitable::HitableList;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::mesh::{Triangle, TriangleMesh, Vertex};
+use crate::myvec::Vec3;
+use crate::scene::SceneError;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The triangles of an OBJ file.
+pub struct Obj {
+    pub triangles: HitableList,
+    /// The triangles whose material glows, also found in `triangles`, to
+    /// be sampled as lights.
+    pub emitters: Vec<Arc<Triangle>>,
+    /// Problems that did not stop the file from loading.
+    pub warnings: Vec<String>,
+}
+
+/// Loads an OBJ file. `mtllib` paths are relative to the OBJ file; faces
+/// before any `usemtl` get `default_material`.
+pub fn load_obj(path: impl AsRef<Path>, default_material: Arc<dyn Material>) -> Result<Obj, SceneError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_obj(&text, dir, default_material)
+}
+
+/// 1-based column of `token`, which must be a slice of `line`.
+fn column(line: &str, token: &str) -> usize {
+    let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
+
+fn parse_error(line: usize, column: usize, message: String) -> SceneError {
+    SceneError::Parse { line, column, message }
+}
+
+/// Parses the numbers after a statement keyword, requiring at least `min`.
+fn numbers(line_no: usize, line: &str, args: &[&str], min: usize) -> Result<Vec<f32>, SceneError> {
+    if args.len() < min {
+        return Err(parse_error(line_no, 1, format!("expected {} numbers", min)));
+    }
+    args.iter().map(|arg| {
+        arg.parse().map_err(|_| parse_error(line_no, column(line, arg), format!("`{}` is not a number", arg)))
+    }).collect()
+}
+
+/// Resolves a 1-based or negative (relative) OBJ index into a buffer of `len`.
+fn index(line_no: usize, line: &str, token: &str, field: &str, len: usize) -> Result<usize, SceneError> {
+    let error = |message| parse_error(line_no, column(line, token), message);
+    let i: i64 = field.parse().map_err(|_| error(format!("`{}` is not an index", field)))?;
+    let resolved = if i > 0 { i - 1 } else { len as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= len as i64 {
+        return Err(error(format!("index {} out of range", i)));
+    }
+    Ok(resolved as usize)
+}
+
+/// Parses OBJ text. `dir` is where `mtllib` files are looked up.
+pub fn parse_obj(text: &str, dir: &Path, default_material: Arc<dyn Material>) -> Result<Obj, SceneError> {
+    let mut mesh = TriangleMesh::default();
+    let mut faces: Vec<([Vertex; 3], Arc<dyn Material>)> = Vec::new();
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let mut current = Arc::clone(&default_material);
+    let mut warnings = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => {
+                let v = numbers(line_no, line, &args, 3)?;
+                mesh.positions.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            "vn" => {
+                let n = numbers(line_no, line, &args, 3)?;
+                mesh.normals.push(Vec3::new(n[0], n[1], n[2]).normalize());
+            }
+            "vt" => {
+                let t = numbers(line_no, line, &args, 1)?;
+                mesh.uvs.push([t[0], t.get(1).copied().unwrap_or(0.0)]);
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(line_no, 1, String::from("a face needs at least 3 vertices")));
+                }
+                let mut corners = Vec::with_capacity(args.len());
+                for token in args.iter() {
+                    let mut fields = token.split('/');
+                    let position = index(line_no, line, token, fields.next().unwrap_or(""), mesh.positions.len())?;
+                    let uv = match fields.next() {
+                        Some("") | None => None,
+                        Some(field) => Some(index(line_no, line, token, field, mesh.uvs.len())?),
+                    };
+                    let normal = match fields.next() {
+                        Some("") | None => None,
+                        Some(field) => Some(index(line_no, line, token, field, mesh.normals.len())?),
+                    };
+                    corners.push(Vertex { position, normal, uv });
+                }
+                for k in 1..corners.len() - 1 {
+                    faces.push(([corners[0], corners[k], corners[k + 1]], Arc::clone(&current)));
+                }
+            }
+            "mtllib" => {
+                for name in args.iter() {
+                    let path = dir.join(name);
+                    let text = fs::read_to_string(&path)?;
+                    let library = parse_mtl(&text).map_err(|err| {
+                        io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err))
+                    })?;
+                    materials.extend(library);
+                }
+            }
+            "usemtl" => {
+                let name = args.first().copied().unwrap_or("");
+                current = match materials.get(name) {
+                    Some(material) => Arc::clone(material),
+                    None => {
+                        warnings.push(format!("line {}: unknown material `{}`, using the default", line_no, name));
+                        Arc::clone(&default_material)
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+
+    let mesh = Arc::new(mesh);
+    let mut triangles = HitableList::default();
+    let mut emitters = Vec::new();
+    for (vertices, material) in faces {
+        let glows = material.emitted(0.0, 0.0, Vec3::default()).length() > 0.0;
+        let triangle = Arc::new(Triangle::new(Arc::clone(&mesh), vertices, material));
+        if glows {
+            emitters.push(Arc::clone(&triangle));
+        }
+        triangles.add(Box::new(triangle));
+    }
+    Ok(Obj { triangles, emitters, warnings })
+}
+
+#[derive(Default)]
+struct MtlDesc {
+    kd: Option<Vec3>,
+    ks: Option<Vec3>,
+    ke: Option<Vec3>,
+    ns: Option<f32>,
+    ni: Option<f32>,
+    d: Option<f32>,
+    illum: Option<u32>,
+}
+
+fn is_black(c: Option<Vec3>) -> bool {
+    c.is_none_or(|c| c.x.max(c.y).max(c.z) <= 0.0)
+}
+
+impl MtlDesc {
+    fn build(&self) -> Arc<dyn Material> {
+        let kd = self.kd.unwrap_or_else(|| Vec3::new(0.8, 0.8, 0.8));
+        if !is_black(self.ke) {
+            Arc::new(DiffuseLight::new(self.ke.unwrap()))
+        } else if matches!(self.illum, Some(4 | 6 | 7 | 9)) || self.d.is_some_and(|d| d < 1.0) {
+            Arc::new(Dielectric::new(self.ni.unwrap_or(1.5)))
+        } else if matches!(self.illum, Some(3 | 5)) || (is_black(self.kd) && !is_black(self.ks)) {
+            // Same width as a Beckmann lobe with this Phong exponent.
+            let fuzz = self.ns.map_or(0.0, |ns| (2.0 / (ns.max(0.0) + 2.0)).sqrt());
+            Arc::new(Metal::new(self.ks.unwrap_or(kd), fuzz))
+        } else {
+            Arc::new(Lambertian::new(kd))
+        }
+    }
+}
+
+/// Parses an MTL material library.
+pub fn parse_mtl(text: &str) -> Result<HashMap<String, Arc<dyn Material>>, SceneError> {
+    let mut descs: Vec<(String, MtlDesc)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            descs.push((args.join(" "), MtlDesc::default()));
+            continue;
+        }
+        let desc = match descs.last_mut() {
+            Some((_, desc)) => desc,
+            None => continue,
+        };
+        let color = |args: &[&str]| numbers(line_no, line, args, 3).map(|c| Some(Vec3::new(c[0], c[1], c[2])));
+        match keyword {
+            "Kd" => desc.kd = color(&args)?,
+            "Ks" => desc.ks = color(&args)?,
+            "Ke" => desc.ke = color(&args)?,
+            "Ns" => desc.ns = Some(numbers(line_no, line, &args, 1)?[0]),
+            "Ni" => desc.ni = Some(numbers(line_no, line, &args, 1)?[0]),
+            "d" => desc.d = Some(numbers(line_no, line, &args, 1)?[0]),
+            "Tr" => desc.d = Some(1.0 - numbers(line_no, line, &args, 1)?[0]),
+            "illum" => desc.illum = Some(numbers(line_no, line, &args, 1)?[0] as u32),
+            _ => {}
+        }
+    }
+    Ok(descs.into_iter().map(|(name, desc)| (name, desc.build())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::{HitRecord, Hitable};
+    use crate::material::Lobe;
+    use crate::ray::Ray;
+    use crate::sampler::Pcg32;
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))
+    }
+
+    const QUAD: &str = "
+# unit square at z = -1, split into two triangles
+v 0 0 -1
+v 1 0 -1
+v 1 1 -1
+v 0 1 -1
+vn 0 0 1
+f 1//1 2//1 3//1 4//1
+";
+
+    #[test]
+    fn polygons_become_triangle_fans() {
+        let list = parse_obj(QUAD, Path::new(""), gray()).unwrap().triangles;
+        assert_eq!(list.length(), 2);
+        for &(x, y) in [(0.9, 0.1), (0.1, 0.9)].iter() {
+            let rec = list.hit(&Ray::new(Vec3::new(x, y, 0.), Vec3::new(0., 0., -1.)), 0.001, f32::MAX).unwrap();
+            assert_eq!(rec.t, 1.0);
+            assert_eq!(rec.normal.z, 1.0);
+        }
+    }
+
+    #[test]
+    fn bad_index_reports_position() {
+        let text = QUAD.replace("f 1//1 2//1 3//1 4//1", "f 1 2 5");
+        match parse_obj(&text, Path::new(""), gray()) {
+            Err(SceneError::Parse { line, column, message }) => {
+                assert_eq!((line, column), (8, 7));
+                assert!(message.contains("out of range"), "{}", message);
+            }
+            _ => panic!("index 5 should be rejected"),
+        }
+    }
+
+    #[test]
+    fn unknown_materials_fall_back_to_the_default() {
+        let text = QUAD.replace("f 1//1", "usemtl chrome\nf 1//1");
+        let obj = parse_obj(&text, Path::new(""), Arc::new(DiffuseLight::new(Vec3::new(2., 2., 2.)))).unwrap();
+        assert_eq!(obj.warnings, vec![String::from("line 8: unknown material `chrome`, using the default")]);
+        // The default glows here, so both halves are lights.
+        assert_eq!((obj.triangles.length(), obj.emitters.len()), (2, 2));
+        assert!(parse_obj(QUAD, Path::new(""), gray()).unwrap().emitters.is_empty());
+    }
+
+    #[test]
+    fn mtl_maps_onto_materials() {
+        let text = "
+newmtl glass
+Ni 1.33
+d 0.2
+newmtl lamp
+Ke 4 4 4
+newmtl bad
+Kd 0.5 x 0.5
+newmtl steel
+Kd 0 0 0
+Ks 0.7 0.6 0.5
+Ns 98
+";
+        match parse_mtl(text) {
+            Err(SceneError::Parse { line, column, .. }) => assert_eq!((line, column), (8, 8)),
+            _ => panic!("bad color should be rejected"),
+        }
+        let library = parse_mtl(&text.replace("0.5 x", "0.5 0.5")).unwrap();
+        assert_eq!(library.len(), 4);
+        let lamp = library["lamp"].emitted(0., 0., Vec3::default());
+        assert_eq!((lamp.x, lamp.y, lamp.z), (4., 4., 4.));
+
+        // Materials cannot be told apart by type, so compare what they do
+        // with what the expected material does.
+        let rec = HitRecord {
+            t: 1.0, p: Vec3::default(), normal: Vec3::new(0., 0., 1.), u: 0.0, v: 0.0, material: gray(), object_id: 0,
+        };
+        let r_in = Ray::new(Vec3::new(-0.6, 0., 1.), Vec3::new(0.6, 0., -1.));
+        let specular = |material: &dyn Material, seed: u64| {
+            let scatter = material.scatter(&r_in, &rec, &mut Pcg32::new(seed, 0)).unwrap();
+            match scatter.lobe {
+                Lobe::Specular(r) => (scatter.attenuation, r.direction),
+                Lobe::Sampled(_) => panic!("glass should scatter specularly"),
+            }
+        };
+        let glass = &library["glass"];
+        assert!((glass.albedo(&rec) - Vec3::new(1., 1., 1.)).length() < 1e-6);
+        let (water, crown) = (Dielectric::new(1.33), Dielectric::new(1.5));
+        let mut index_matters = false;
+        for seed in 0..32 {
+            let (attenuation, direction) = specular(glass.as_ref(), seed);
+            assert!((attenuation - Vec3::new(1., 1., 1.)).length() < 1e-6);
+            assert!((direction - specular(&water, seed).1).length() < 1e-6);
+            index_matters |= (direction - specular(&crown, seed).1).length() > 1e-3;
+        }
+        assert!(index_matters);
+
+        let steel = &library["steel"];
+        let ks = Vec3::new(0.7, 0.6, 0.5);
+        let scatter = steel.scatter(&r_in, &rec, &mut Pcg32::new(0, 0)).unwrap();
+        assert!(!scatter.is_specular());
+        assert!((scatter.attenuation - ks).length() < 1e-6);
+        let (expected, wider) = (Metal::new(ks, (2.0f32 / 100.0).sqrt()), Metal::new(ks, 0.3));
+        for direction in [Vec3::new(0.6, 0., 1.), Vec3::new(0.5, 0.1, 1.), Vec3::new(0., 0.3, 1.)].iter() {
+            let scattered = Ray::new(rec.p, *direction);
+            let pdf = steel.scattering_pdf(&r_in, &rec, &scattered);
+            assert!((pdf - expected.scattering_pdf(&r_in, &rec, &scattered)).abs() <= 1e-4 * pdf, "{:?}", direction);
+            assert!((pdf - wider.scattering_pdf(&r_in, &rec, &scattered)).abs() > 1e-2 * pdf, "{:?}", direction);
+        }
+    }
+}
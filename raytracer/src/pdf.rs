@@ -0,0 +1,218 @@
+//! Probability densities over directions, used to importance-sample
+//! scattered rays and lights. Densities are per unit solid angle.
+
+use crate::hitable::Hitable;
+use crate::myvec::Vec3;
+use crate::sampler::{random_unit_vector, Sampler};
+use std::f32::consts::PI;
+
+pub trait Pdf {
+    /// Density with which `generate` returns `direction`, which need not
+    /// be normalized.
+    fn value(&self, direction: Vec3) -> f32;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+/// Cosine-weighted hemisphere around a unit `normal`: the ideal density
+/// for a Lambertian surface.
+pub struct CosinePdf {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl CosinePdf {
+    pub fn new(normal: Vec3) -> Self {
+        let (u, v) = normal.basis();
+        CosinePdf { u, v, w: normal }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f32 {
+        let cosine = direction.normalize().dot(self.w);
+        cosine.max(0.0) / PI
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        // Malley's method: a uniform point on the disk, lifted onto the
+        // hemisphere.
+        let phi = 2.0 * PI * sampler.next_f32();
+        let r2 = sampler.next_f32();
+        let r = r2.sqrt();
+        self.u * (r * phi.cos()) + self.v * (r * phi.sin()) + self.w * (1.0 - r2).sqrt()
+    }
+}
+
+/// Every direction equally likely, as for isotropic media.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        random_unit_vector(sampler)
+    }
+}
+
+/// GGX (Trowbridge-Reitz) normal distribution for roughness `alpha`, at a
+/// microfacet normal making cosine `cos_h` with the surface normal.
+pub(crate) fn ggx_d(alpha: f32, cos_h: f32) -> f32 {
+    if cos_h <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = cos_h * cos_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Smith masking term of GGX for a direction making cosine `cos_v` with
+/// the surface normal.
+pub(crate) fn smith_g1(alpha: f32, cos_v: f32) -> f32 {
+    if cos_v <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    2.0 * cos_v / (cos_v + (a2 + (1.0 - a2) * cos_v * cos_v).sqrt())
+}
+
+/// Reflections off GGX microfacets: a facet normal is drawn in proportion
+/// to its projected area and the outgoing direction `wo` (unit, pointing
+/// away from the surface) is mirrored about it.
+pub struct GgxPdf {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    wo: Vec3,
+    alpha: f32,
+}
+
+impl GgxPdf {
+    pub fn new(normal: Vec3, wo: Vec3, alpha: f32) -> Self {
+        let (u, v) = normal.basis();
+        GgxPdf { u, v, w: normal, wo, alpha }
+    }
+}
+
+impl Pdf for GgxPdf {
+    fn value(&self, direction: Vec3) -> f32 {
+        let h = (self.wo + direction.normalize()).normalize();
+        let wo_h = self.wo.dot(h).abs();
+        if !wo_h.is_finite() || wo_h == 0.0 {
+            return 0.0;
+        }
+        // A facet turned away from `wo` mirrors it to the same direction as
+        // the opposite facet; only the one facing out can have been drawn.
+        let cos_h = h.dot(self.w).abs();
+        ggx_d(self.alpha, cos_h) * cos_h / (4.0 * wo_h)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let phi = 2.0 * PI * sampler.next_f32();
+        let r = sampler.next_f32();
+        let tan2 = self.alpha * self.alpha * r / (1.0 - r);
+        let cos_h = 1.0 / (1.0 + tan2).sqrt();
+        let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+        let h = self.u * (sin_h * phi.cos()) + self.v * (sin_h * phi.sin()) + self.w * cos_h;
+        h * (2.0 * self.wo.dot(h)) - self.wo
+    }
+}
+
+/// Draws from `a` with probability `weight` and from `b` otherwise.
+pub struct MixturePdf<'a> {
+    a: Box<dyn Pdf + 'a>,
+    b: Box<dyn Pdf + 'a>,
+    weight: f32,
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(a: Box<dyn Pdf + 'a>, b: Box<dyn Pdf + 'a>, weight: f32) -> Self {
+        MixturePdf { a, b, weight }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: Vec3) -> f32 {
+        self.weight * self.a.value(direction) + (1.0 - self.weight) * self.b.value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.next_f32() < self.weight {
+            self.a.generate(sampler)
+        } else {
+            self.b.generate(sampler)
+        }
+    }
+}
+
+/// Directions from `origin` towards random points of `object`, usually the
+/// list of lights.
+pub struct HitablePdf<'a> {
+    object: &'a dyn Hitable,
+    origin: Vec3,
+}
+
+impl<'a> HitablePdf<'a> {
+    pub fn new(object: &'a dyn Hitable, origin: Vec3) -> Self {
+        HitablePdf { object, origin }
+    }
+}
+
+impl Pdf for HitablePdf<'_> {
+    fn value(&self, direction: Vec3) -> f32 {
+        self.object.pdf_value(self.origin, direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(self.origin, sampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::Sphere;
+    use crate::material::Lambertian;
+    use crate::sampler::Pcg32;
+    use std::sync::Arc;
+
+    /// Monte Carlo estimate of the integral of `pdf` over all directions.
+    fn integral(pdf: &dyn Pdf) -> f32 {
+        let mut sampler = Pcg32::new(2, 0);
+        let n = 200_000;
+        let total: f32 = (0..n).map(|_| pdf.value(random_unit_vector(&mut sampler))).sum();
+        total / n as f32 * 4.0 * PI
+    }
+
+    #[test]
+    fn densities_integrate_to_one() {
+        let normal = Vec3::new(1., 2., 2.).normalize();
+        let wo = Vec3::new(0., 1., 2.).normalize();
+        let lamp = Sphere::new(Vec3::new(0., 0., 4.), 1.0, Arc::new(Lambertian::new(Vec3::default())));
+        let mixture = MixturePdf::new(Box::new(CosinePdf::new(normal)), Box::new(HitablePdf::new(&lamp, Vec3::default())), 0.3);
+        let pdfs: [(&str, &dyn Pdf); 4] = [
+            ("cosine", &CosinePdf::new(normal)),
+            ("sphere", &SpherePdf),
+            ("ggx", &GgxPdf::new(normal, wo, 0.5)),
+            ("mixture", &mixture),
+        ];
+        for (name, pdf) in pdfs.iter() {
+            let value = integral(*pdf);
+            assert!((value - 1.0).abs() < 0.03, "{} integrates to {}", name, value);
+        }
+    }
+
+    #[test]
+    fn generated_directions_have_positive_density() {
+        let normal = Vec3::new(0., 0., 1.);
+        let pdfs: [&dyn Pdf; 2] = [&CosinePdf::new(normal), &GgxPdf::new(normal, Vec3::new(0.6, 0., 0.8), 0.2)];
+        let mut sampler = Pcg32::new(3, 0);
+        for pdf in pdfs.iter() {
+            for _ in 0..1000 {
+                assert!(pdf.value(pdf.generate(&mut sampler)) > 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Quasi-Monte Carlo samplers. They spread the samples of a pixel more
+//! evenly than independent random numbers, so images converge faster.
+//!
+//! Each sample of a path reads dimension after dimension from its sampler:
+//! the pixel position first, then the lens, then whatever the integrator
+//! asks for. `Sampler::start_sample` moves to the next sample and back to
+//! the first dimension, so the same decision gets the same dimension in
+//! every sample, which is where the even spreading pays off.
+
+use crate::noise::mix;
+use crate::sampler::Sampler;
+use std::sync::OnceLock;
+
+fn hash(a: u64, b: u64) -> u64 {
+    mix(a ^ mix(b.wrapping_add(0x9e37_79b9_7f4a_7c15)))
+}
+
+fn to_unit(bits: u32) -> f32 {
+    (bits >> 8) as f32 * (1.0 / 16_777_216.0)
+}
+
+/// Element `i` of a random permutation of `0..len` chosen by `seed`, after
+/// Andrew Kensler's "Correlated Multi-Jittered Sampling".
+fn permute(mut i: u32, len: u32, seed: u32) -> u32 {
+    let p = seed;
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+    (i + p) % len
+}
+
+/// Owen scrambling of the bits of `x`, most significant first, with the
+/// hash of Brent Burley's "Practical Hash-based Owen Scrambling".
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+/// The first two dimensions of the Sobol sequence, as 32-bit fractions.
+fn sobol_2d(index: u32) -> (u32, u32) {
+    let mut y = 0;
+    let mut v = 1 << 31;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= v;
+        }
+        v ^= v >> 1;
+        bits >>= 1;
+    }
+    (index.reverse_bits(), y)
+}
+
+/// Dimension `dim` of sample `index` of a padded Sobol sequence: every pair
+/// of dimensions is an independently shuffled and Owen-scrambled copy of
+/// the two-dimensional Sobol points.
+fn padded_sobol(index: u32, dim: u32, seed: u64) -> f32 {
+    let pair = hash(seed, u64::from(dim / 2));
+    let index = owen_scramble(index, pair as u32);
+    let (x, y) = sobol_2d(index);
+    let bits = if dim & 1 == 0 { x } else { y };
+    to_unit(owen_scramble(bits, (pair >> 32) as u32 ^ dim))
+}
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Radical inverse of `index` in `base` with every digit permuted by a
+/// hash of `seed` and the digits before it, which is Owen scrambling.
+fn owen_radical_inverse(base: u32, mut index: u64, seed: u64) -> f32 {
+    let base64 = u64::from(base);
+    let digits = (32.0 * 2f64.ln() / f64::from(base).ln()).ceil() as u32;
+    let inv_base = 1.0 / f64::from(base);
+    let mut scale = 1.0;
+    let mut reversed = 0u64;
+    for _ in 0..digits {
+        let digit = (index % base64) as u32;
+        index /= base64;
+        let digit = permute(digit, base, hash(seed, reversed) as u32);
+        reversed = reversed * base64 + u64::from(digit);
+        scale *= inv_base;
+    }
+    ((reversed as f64 * scale) as f32).min(1.0 - f32::EPSILON / 2.0)
+}
+
+/// Jittered samples stratified in every dimension: the `spp` samples of a
+/// pixel fall into different `1/spp` slices, in an order shuffled anew for
+/// every dimension.
+pub struct StratifiedSampler {
+    seed: u64,
+    spp: u32,
+    index: u32,
+    dim: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, spp: usize) -> Self {
+        StratifiedSampler { seed, spp: spp.max(1) as u32, index: 0, dim: 0 }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn next_f32(&mut self) -> f32 {
+        let dim_seed = hash(self.seed, u64::from(self.dim));
+        self.dim += 1;
+        // Samples past `spp` start a new round of slices.
+        let round = self.index / self.spp;
+        let stratum = permute(self.index % self.spp, self.spp, hash(dim_seed, u64::from(round)) as u32);
+        let jitter = to_unit(hash(dim_seed, u64::from(self.index) | 1 << 32) as u32);
+        ((stratum as f32 + jitter) / self.spp as f32).min(1.0 - f32::EPSILON / 2.0)
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        self.index = index as u32;
+        self.dim = 0;
+    }
+}
+
+/// The Halton sequence with Owen scrambling. Dimension `d` uses the `d`th
+/// prime as its base; past the table the bases repeat with new scrambles.
+pub struct HaltonSampler {
+    seed: u64,
+    index: u32,
+    dim: u32,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler { seed, index: 0, dim: 0 }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn next_f32(&mut self) -> f32 {
+        let base = PRIMES[self.dim as usize % PRIMES.len()];
+        let value = owen_radical_inverse(base, u64::from(self.index), hash(self.seed, u64::from(self.dim)));
+        self.dim += 1;
+        value
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        self.index = index as u32;
+        self.dim = 0;
+    }
+}
+
+/// Padded Sobol points with Owen scrambling, after Burley: the strongest
+/// stratification of the lot for power-of-two sample counts.
+pub struct SobolSampler {
+    seed: u64,
+    index: u32,
+    dim: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        SobolSampler { seed, index: 0, dim: 0 }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn next_f32(&mut self) -> f32 {
+        let value = padded_sobol(self.index, self.dim, self.seed);
+        self.dim += 1;
+        value
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        self.index = index as u32;
+        self.dim = 0;
+    }
+}
+
+const MASK_SIZE: usize = 64;
+
+/// Blue-noise ranks for a `MASK_SIZE` square tile, built once with Robert
+/// Ulichney's void-and-cluster method: every pixel in turn joins the
+/// biggest hole left between those already placed.
+fn blue_noise_mask() -> &'static [f32] {
+    static MASK: OnceLock<Vec<f32>> = OnceLock::new();
+    MASK.get_or_init(|| {
+        let n = MASK_SIZE * MASK_SIZE;
+        // Gaussian energy of a point at every wrapped offset.
+        let kernel: Vec<f32> = (0..n).map(|k| {
+            let wrap = |d: usize| d.min(MASK_SIZE - d) as f32;
+            let (dx, dy) = (wrap(k % MASK_SIZE), wrap(k / MASK_SIZE));
+            (-(dx * dx + dy * dy) / (2.0 * 1.5 * 1.5)).exp()
+        }).collect();
+        let mut energy = vec![0.0f32; n];
+        let mut rank = vec![u32::MAX; n];
+        // Start from a pixel picked by hash so the mask is the same every run.
+        let mut next = (mix(MASK_SIZE as u64) % n as u64) as usize;
+        for r in 0..n as u32 {
+            rank[next] = r;
+            let (px, py) = (next % MASK_SIZE, next / MASK_SIZE);
+            for (k, e) in energy.iter_mut().enumerate() {
+                let dx = (k % MASK_SIZE + MASK_SIZE - px) % MASK_SIZE;
+                let dy = (k / MASK_SIZE + MASK_SIZE - py) % MASK_SIZE;
+                *e += kernel[dy * MASK_SIZE + dx];
+            }
+            next = (0..n).filter(|&k| rank[k] == u32::MAX)
+                .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap_or(0);
+        }
+        rank.iter().map(|&r| (r as f32 + 0.5) / n as f32).collect()
+    })
+}
+
+/// Blue-noise dithered sampling after Georgiev and Fajardo: every pixel
+/// uses the same Sobol points, shifted by a blue-noise value that differs
+/// for every dimension. Neighbouring pixels get very different shifts, so
+/// what error is left looks like fine grain rather than blotches.
+pub struct BlueNoiseSampler {
+    seed: u64,
+    x: usize,
+    y: usize,
+    index: u32,
+    dim: u32,
+}
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u64, i: usize, j: usize) -> Self {
+        BlueNoiseSampler { seed, x: i, y: j, index: 0, dim: 0 }
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn next_f32(&mut self) -> f32 {
+        let offset = hash(self.seed, u64::from(self.dim) | 1 << 40);
+        let mx = (self.x + offset as usize % MASK_SIZE) % MASK_SIZE;
+        let my = (self.y + (offset >> 32) as usize % MASK_SIZE) % MASK_SIZE;
+        let shift = blue_noise_mask()[my * MASK_SIZE + mx];
+        let value = padded_sobol(self.index, self.dim, self.seed) + shift;
+        self.dim += 1;
+        let value = if value >= 1.0 { value - 1.0 } else { value };
+        value.min(1.0 - f32::EPSILON / 2.0)
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        self.index = index as u32;
+        self.dim = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::SamplerKind;
+
+    #[test]
+    fn permutations_are_bijective() {
+        for &len in [1, 2, 7, 64, 100].iter() {
+            let mut seen = vec![false; len as usize];
+            for i in 0..len {
+                seen[permute(i, len, 0xdead_beef) as usize] = true;
+            }
+            assert!(seen.iter().all(|&s| s), "{}", len);
+        }
+    }
+
+    #[test]
+    fn sobol_points_are_stratified() {
+        // Any 16 consecutive aligned scrambled Sobol points put one point in
+        // each cell of a 4x4 grid.
+        let mut cells = [false; 16];
+        for index in 0..16 {
+            let x = padded_sobol(index, 4, 9);
+            let y = padded_sobol(index, 5, 9);
+            cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] = true;
+        }
+        assert!(cells.iter().all(|&c| c));
+    }
+
+    #[test]
+    fn blue_noise_mask_is_a_permutation() {
+        let mut ranks: Vec<f32> = blue_noise_mask().to_vec();
+        ranks.sort_by(f32::total_cmp);
+        for (k, &r) in ranks.iter().enumerate() {
+            assert_eq!(r, (k as f32 + 0.5) / (MASK_SIZE * MASK_SIZE) as f32);
+        }
+    }
+
+    /// Root mean square error of `spp`-sample estimates of a smooth
+    /// four-dimensional integral over many pixels.
+    fn rms_error(kind: SamplerKind, spp: usize) -> f32 {
+        let f = |x: &[f32]| (x[0] * x[1]).sqrt() + (3.0 * x[2]).sin() * x[3];
+        let exact = 4.0 / 9.0 + (1.0 - 3f32.cos()) / 6.0;
+        let pixels = 256;
+        let total: f32 = (0..pixels).map(|p| {
+            let mut sampler = kind.for_pixel(5, p % 16, p / 16, spp);
+            let mut sum = 0.0;
+            for k in 0..spp {
+                sampler.start_sample(k);
+                let x: Vec<f32> = (0..4).map(|_| sampler.next_f32()).collect();
+                assert!(x.iter().all(|&v| (0.0..1.0).contains(&v)), "{:?}", kind);
+                sum += f(&x);
+            }
+            let error = sum / spp as f32 - exact;
+            error * error
+        }).sum();
+        (total / pixels as f32).sqrt()
+    }
+
+    #[test]
+    fn low_discrepancy_samplers_converge_faster() {
+        let uniform = rms_error(SamplerKind::Independent, 64);
+        for &kind in [SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol, SamplerKind::BlueNoise].iter() {
+            let error = rms_error(kind, 64);
+            assert!(error < 0.5 * uniform, "{:?}: {} vs uniform {}", kind, error, uniform);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::myvec::Vec3;
+use crate::sampler::Sampler;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+    /// Instant within the camera's shutter interval at which the ray exists.
+    pub time: f32,
+    /// Optical depth of participating media the ray crosses before it
+    /// scatters. Infinite unless drawn with `with_free_path`, so media let
+    /// such rays through.
+    pub optical_depth: f32,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Vec3, direction: Vec3, time: f32) -> Self {
+        Ray {origin, direction, time, optical_depth: f32::INFINITY}
+    }
+
+    /// The same ray with an optical depth drawn from `sampler`, exponentially
+    /// distributed with mean 1.
+    pub fn with_free_path(self, sampler: &mut dyn Sampler) -> Self {
+        Ray { optical_depth: -(1.0 - sampler.next_f32()).ln(), ..self }
+    }
+    
+    pub fn point_at_paramter(&self, parameter: f32) -> Vec3 {
+        self.origin + self.direction * parameter
+    }
+}
+
+
+
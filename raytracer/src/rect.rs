@@ -0,0 +1,264 @@
+use crate::aabb::Aabb;
+use crate::hitable::{area_pdf, HitRecord, Hitable, HitableList};
+use crate::material::Material;
+use crate::myvec::Vec3;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use std::sync::Arc;
+
+/// Boxes of axis-aligned rectangles get this much thickness, so that the
+/// BVH never sees a box with zero volume.
+const PAD: f32 = 0.0001;
+
+/// Unit vector along `axis`.
+fn axis_vec(axis: usize) -> Vec3 {
+    let mut v = [0.0; 3];
+    v[axis] = 1.0;
+    Vec3::new(v[0], v[1], v[2])
+}
+
+/// Intersects `r` with the rectangle `[a0, a1] x [b0, b1]` on the plane
+/// where coordinate `c` equals `k`, with `a` and `b` the other two axes.
+#[allow(clippy::too_many_arguments)]
+fn hit_rect(r: &Ray, t_min: f32, t_max: f32, (a, b, c): (usize, usize, usize),
+            (a0, a1, b0, b1): (f32, f32, f32, f32), k: f32, material: &Arc<dyn Material>) -> Option<HitRecord> {
+    let t = (k - r.origin[c]) / r.direction[c];
+    if !(t > t_min && t < t_max) {
+        return None;
+    }
+    let p = r.point_at_paramter(t);
+    if p[a] < a0 || p[a] > a1 || p[b] < b0 || p[b] > b1 {
+        return None;
+    }
+    let u = (p[a] - a0) / (a1 - a0);
+    let v = (p[b] - b0) / (b1 - b0);
+    Some(HitRecord { t, p, normal: axis_vec(c), u, v, material: Arc::clone(material), object_id: 0 })
+}
+
+/// Density of `rect_random` picking `direction` from `origin`.
+fn rect_pdf(rect: &dyn Hitable, origin: Vec3, direction: Vec3, (a0, a1, b0, b1): (f32, f32, f32, f32)) -> f32 {
+    match rect.hit(&Ray::new(origin, direction), 0.001, f32::MAX) {
+        Some(rec) => area_pdf(direction, rec.t, rec.normal, (a1 - a0) * (b1 - b0)),
+        None => 0.0,
+    }
+}
+
+/// Direction from `origin` to a uniformly chosen point of the rectangle.
+fn rect_random(origin: Vec3, (a, b, c): (usize, usize, usize), (a0, a1, b0, b1): (f32, f32, f32, f32), k: f32,
+               sampler: &mut dyn Sampler) -> Vec3 {
+    let mut p = [0.0; 3];
+    p[a] = sampler.next_range(a0, a1);
+    p[b] = sampler.next_range(b0, b1);
+    p[c] = k;
+    Vec3::new(p[0], p[1], p[2]) - origin
+}
+
+fn rect_box((a, b, c): (usize, usize, usize), (a0, a1, b0, b1): (f32, f32, f32, f32), k: f32) -> Aabb {
+    let mut min = [0.0; 3];
+    let mut max = [0.0; 3];
+    min[a] = a0;
+    max[a] = a1;
+    min[b] = b0;
+    max[b] = b1;
+    min[c] = k - PAD;
+    max[c] = k + PAD;
+    Aabb::new(Vec3::new(min[0], min[1], min[2]), Vec3::new(max[0], max[1], max[2]))
+}
+
+/// Rectangle `[x0, x1] x [y0, y1]` in the plane `z = k`, facing `+z`.
+pub struct XYRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub y0: f32,
+    pub y1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl XYRect {
+    pub fn new(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        XYRect { x0, x1, y0, y1, k, material }
+    }
+}
+
+impl Hitable for XYRect {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_rect(r, t_min, t_max, (0, 1, 2), (self.x0, self.x1, self.y0, self.y1), self.k, &self.material)
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        Some(rect_box((0, 1, 2), (self.x0, self.x1, self.y0, self.y1), self.k))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        rect_pdf(self, origin, direction, (self.x0, self.x1, self.y0, self.y1))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        rect_random(origin, (0, 1, 2), (self.x0, self.x1, self.y0, self.y1), self.k, sampler)
+    }
+}
+
+/// Rectangle `[x0, x1] x [z0, z1]` in the plane `y = k`, facing `+y`.
+pub struct XZRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl XZRect {
+    pub fn new(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        XZRect { x0, x1, z0, z1, k, material }
+    }
+}
+
+impl Hitable for XZRect {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_rect(r, t_min, t_max, (0, 2, 1), (self.x0, self.x1, self.z0, self.z1), self.k, &self.material)
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        Some(rect_box((0, 2, 1), (self.x0, self.x1, self.z0, self.z1), self.k))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        rect_pdf(self, origin, direction, (self.x0, self.x1, self.z0, self.z1))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        rect_random(origin, (0, 2, 1), (self.x0, self.x1, self.z0, self.z1), self.k, sampler)
+    }
+}
+
+/// Rectangle `[y0, y1] x [z0, z1]` in the plane `x = k`, facing `+x`.
+pub struct YZRect {
+    pub y0: f32,
+    pub y1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl YZRect {
+    pub fn new(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        YZRect { y0, y1, z0, z1, k, material }
+    }
+}
+
+impl Hitable for YZRect {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_rect(r, t_min, t_max, (1, 2, 0), (self.y0, self.y1, self.z0, self.z1), self.k, &self.material)
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        Some(rect_box((1, 2, 0), (self.y0, self.y1, self.z0, self.z1), self.k))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        rect_pdf(self, origin, direction, (self.y0, self.y1, self.z0, self.z1))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        rect_random(origin, (1, 2, 0), (self.y0, self.y1, self.z0, self.z1), self.k, sampler)
+    }
+}
+
+/// Turns the normals of the wrapped object around, e.g. so that the walls
+/// of a room face inwards.
+pub struct FlipNormals {
+    inner: Box<dyn Hitable>,
+}
+
+impl FlipNormals {
+    pub fn new(inner: Box<dyn Hitable>) -> Self {
+        FlipNormals { inner }
+    }
+}
+
+impl Hitable for FlipNormals {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.inner.hit(r, t_min, t_max).map(|rec| HitRecord { normal: -rec.normal, ..rec })
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.inner.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        self.inner.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.inner.random(origin, sampler)
+    }
+}
+
+/// Axis-aligned box from `min` to `max`, made of six outward-facing
+/// rectangles (the book's `box`, renamed to stay clear of `std::boxed::Box`).
+pub struct Cuboid {
+    min: Vec3,
+    max: Vec3,
+    sides: HitableList,
+}
+
+impl Cuboid {
+    pub fn new(min: Vec3, max: Vec3, material: Arc<dyn Material>) -> Self {
+        let mut sides = HitableList::default();
+        sides.add(Box::new(XYRect::new(min.x, max.x, min.y, max.y, max.z, Arc::clone(&material))));
+        sides.add(Box::new(FlipNormals::new(Box::new(XYRect::new(min.x, max.x, min.y, max.y, min.z, Arc::clone(&material))))));
+        sides.add(Box::new(XZRect::new(min.x, max.x, min.z, max.z, max.y, Arc::clone(&material))));
+        sides.add(Box::new(FlipNormals::new(Box::new(XZRect::new(min.x, max.x, min.z, max.z, min.y, Arc::clone(&material))))));
+        sides.add(Box::new(YZRect::new(min.y, max.y, min.z, max.z, max.x, Arc::clone(&material))));
+        sides.add(Box::new(FlipNormals::new(Box::new(YZRect::new(min.y, max.y, min.z, max.z, min.x, material)))));
+        Cuboid { min, max, sides }
+    }
+}
+
+impl Hitable for Cuboid {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.sides.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    fn white() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Vec3::new(0.73, 0.73, 0.73)))
+    }
+
+    #[test]
+    fn rect_hit_gives_uv_and_normal() {
+        let rect = XZRect::new(0., 2., 0., 4., 1., white());
+        let rec = rect.hit(&Ray::new(Vec3::new(0.5, 3., 1.), Vec3::new(0., -1., 0.)), 0.001, f32::MAX).unwrap();
+        assert_eq!(rec.t, 2.0);
+        assert_eq!((rec.u, rec.v), (0.25, 0.25));
+        assert_eq!((rec.normal.x, rec.normal.y, rec.normal.z), (0., 1., 0.));
+        assert!(rect.hit(&Ray::new(Vec3::new(3., 3., 1.), Vec3::new(0., -1., 0.)), 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn cuboid_normals_face_outwards() {
+        let cuboid = Cuboid::new(Vec3::new(0., 0., 0.), Vec3::new(1., 1., 1.), white());
+        let directions = [Vec3::new(1., 0., 0.), Vec3::new(0., 1., 0.), Vec3::new(0., 0., 1.)];
+        for d in directions.iter() {
+            for &sign in [1.0, -1.0].iter() {
+                let d = *d * sign;
+                let origin = Vec3::new(0.5, 0.5, 0.5) - d * 2.0;
+                let rec = cuboid.hit(&Ray::new(origin, d), 0.001, f32::MAX).unwrap();
+                assert_eq!(rec.t, 1.5);
+                assert!(rec.normal.dot(d) < 0.0);
+            }
+        }
+    }
+}
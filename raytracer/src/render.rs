@@ -0,0 +1,296 @@
+use crate::camera::Camera;
+use crate::film::{Film, Filter};
+use crate::hitable::{Hitable, HitableList};
+use crate::image::Framebuffer;
+use crate::integrator::Integrator;
+use crate::ray::Ray;
+use crate::sampler::{Sampler, SamplerKind};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Keeps sampling pixels whose estimated error is still above `threshold`
+/// (see `Film::relative_error`), in rounds of `ns` samples, until they
+/// have `max_ns` samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSettings {
+    pub threshold: f32,
+    pub max_ns: usize,
+}
+
+pub struct RenderSettings {
+    pub nx: usize,
+    pub ny: usize,
+    /// Samples per pixel, or the least any pixel gets when sampling is
+    /// adaptive.
+    pub ns: usize,
+    pub adaptive: Option<AdaptiveSettings>,
+    pub integrator: Integrator,
+    /// How samples are weighted into the pixels around them.
+    pub filter: Filter,
+    pub sampler: SamplerKind,
+    pub seed: u64,
+    /// Worker threads; 0 means one per available core.
+    pub threads: usize,
+    pub tile_size: usize,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            nx: 200,
+            ny: 100,
+            ns: 10,
+            adaptive: None,
+            integrator: Integrator::default(),
+            filter: Filter::default(),
+            sampler: SamplerKind::default(),
+            seed: 0,
+            threads: 0,
+            tile_size: 16,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub(crate) fn thread_count(&self) -> usize {
+        if self.threads > 0 {
+            self.threads
+        } else {
+            thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+        }
+    }
+
+    /// Passes of `render_pass` after which every pixel has had all the
+    /// samples it can get: 1, or with adaptive sampling enough to reach
+    /// `max_ns`.
+    pub fn passes(&self) -> usize {
+        self.max_ns().div_ceil(self.ns.max(1))
+    }
+
+    /// Most samples any pixel gets.
+    pub(crate) fn max_ns(&self) -> usize {
+        let ns = self.ns.max(1);
+        self.adaptive.map_or(ns, |adaptive| adaptive.max_ns.max(ns))
+    }
+}
+
+struct Tile {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+fn tiles(nx: usize, ny: usize, size: usize) -> Vec<Tile> {
+    let size = size.max(1);
+    let mut tiles = Vec::new();
+    for y0 in (0..ny).step_by(size) {
+        for x0 in (0..nx).step_by(size) {
+            tiles.push(Tile { x0, y0, x1: (x0 + size).min(nx), y1: (y0 + size).min(ny) });
+        }
+    }
+    tiles
+}
+
+/// Starts sample `k` of pixel `(i, j)`, counted from the top-left corner,
+/// and returns its jittered raster position and camera ray.
+pub(crate) fn camera_sample(camera: &Camera, settings: &RenderSettings, sampler: &mut dyn Sampler,
+                            i: usize, j: usize, k: usize) -> (f32, f32, Ray) {
+    // Keeps rounding from pushing a sample into the next pixel, whose count
+    // it would then raise.
+    let jitter = |n: usize, u: f32| (n as f32 + u).min(((n + 1) as f32).next_down());
+    sampler.start_sample(k);
+    let x = jitter(i, sampler.next_f32());
+    let y = jitter(j, sampler.next_f32());
+    // The camera's image plane has v going up from the bottom edge.
+    let r = camera.get_ray(x / settings.nx as f32, 1.0 - y / settings.ny as f32, sampler);
+    (x, y, r)
+}
+
+/// What every pixel of one render is drawn from.
+struct Job<'a> {
+    world: &'a dyn Hitable,
+    lights: &'a HitableList,
+    camera: &'a Camera,
+    settings: &'a RenderSettings,
+}
+
+impl Job<'_> {
+    /// Adds the jittered samples numbered `samples` from inside pixel
+    /// `(i, j)`, counted from the top-left corner, to `film`.
+    fn add_samples(&self, sampler: &mut dyn Sampler, film: &mut Film, i: usize, j: usize, samples: Range<usize>) {
+        for k in samples {
+            let (x, y, r) = camera_sample(self.camera, self.settings, sampler, i, j, k);
+            film.add_sample(x, y, self.settings.integrator.color(&r, self.world, self.lights, sampler));
+        }
+    }
+
+    /// Calls `pixel(film, block, i, j)` for every pixel, on worker threads
+    /// that pull tiles from a shared queue. `block` is the tile's own film,
+    /// merged into `film` in tile order once all tiles are done.
+    fn run<F, P>(&self, film: &mut Film, progress: F, pixel: P)
+    where
+        F: Fn(usize, usize) + Sync,
+        P: Fn(&Film, &mut Film, usize, usize) + Sync,
+    {
+        let settings = self.settings;
+        let tiles = tiles(settings.nx, settings.ny, settings.tile_size);
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let finished: Mutex<Vec<Option<Film>>> = Mutex::new(tiles.iter().map(|_| None).collect());
+        let base = &*film;
+
+        thread::scope(|s| {
+            for _ in 0..settings.thread_count() {
+                s.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let tile = match tiles.get(index) {
+                        Some(tile) => tile,
+                        None => break,
+                    };
+                    let mut block = base.tile(tile.x0..tile.x1, tile.y0..tile.y1);
+                    for j in tile.y0..tile.y1 {
+                        for i in tile.x0..tile.x1 {
+                            pixel(base, &mut block, i, j);
+                        }
+                    }
+                    finished.lock().unwrap()[index] = Some(block);
+                    progress(done.fetch_add(1, Ordering::Relaxed) + 1, tiles.len());
+                });
+            }
+        });
+
+        for block in finished.into_inner().unwrap().iter().flatten() {
+            film.merge(block);
+        }
+    }
+}
+
+/// Renders the image on a pool of worker threads that pull tiles from a
+/// shared queue and returns the linear, unclamped image. `lights` are the
+/// emitters worth sampling directly; they must also be part of `world`.
+///
+/// Every pixel gets its own generator seeded from `settings.seed`, and
+/// tiles are merged into the film in a fixed order once all are done, so
+/// the result does not depend on the thread count or the tile order.
+pub fn render(world: &dyn Hitable, lights: &HitableList, camera: &Camera, settings: &RenderSettings) -> Framebuffer {
+    render_with_progress(world, lights, camera, settings, |_, _| {})
+}
+
+/// Like `render`, but calls `progress(done, total)` from the worker
+/// threads each time a tile finishes.
+pub fn render_with_progress<F>(world: &dyn Hitable, lights: &HitableList, camera: &Camera, settings: &RenderSettings, progress: F) -> Framebuffer
+where
+    F: Fn(usize, usize) + Sync,
+{
+    render_film(world, lights, camera, settings, progress).to_framebuffer()
+}
+
+/// Like `render_with_progress`, but returns the film itself, which also
+/// knows how many samples every pixel got. This is a progressive render of
+/// `settings.passes()` passes, so it gives the same film as rendering them
+/// one by one with `render_pass`.
+pub fn render_film<F>(world: &dyn Hitable, lights: &HitableList, camera: &Camera, settings: &RenderSettings, progress: F) -> Film
+where
+    F: Fn(usize, usize) + Sync,
+{
+    let passes = settings.passes();
+    let mut film = Film::new(settings.nx, settings.ny, settings.filter);
+    for pass in 0..passes {
+        let added = render_pass(world, lights, camera, settings, &mut film, |done, total| {
+            progress(pass * total + done, passes * total);
+        });
+        if added == 0 {
+            // Every pixel converged early, so the passes left are skipped.
+            let total = tiles(settings.nx, settings.ny, settings.tile_size).len() * passes;
+            progress(total, total);
+            break;
+        }
+    }
+    film
+}
+
+/// Renders one pass of a progressive render into `film`, an image-sized
+/// film holding the passes before it, and returns how many samples it
+/// added. Each pass gives every pixel the next `settings.ns` samples;
+/// with adaptive sampling, only pixels still above the threshold and
+/// below the limit get them, and a pass adding nothing means the render
+/// is done.
+///
+/// Samples are numbered on from what the film already holds, so the passes
+/// can be split across runs by saving the film in between: the result
+/// only depends on the number of passes.
+pub fn render_pass<F>(world: &dyn Hitable, lights: &HitableList, camera: &Camera, settings: &RenderSettings,
+                      film: &mut Film, progress: F) -> usize
+where
+    F: Fn(usize, usize) + Sync,
+{
+    let job = Job { world, lights, camera, settings };
+    let max_ns = settings.max_ns();
+    let added = AtomicUsize::new(0);
+    job.run(film, progress, |film, block, i, j| {
+        let first = film.samples(i, j) as usize;
+        let skip = settings.adaptive.is_some_and(|adaptive| {
+            first > 0 && (first >= max_ns || film.relative_error(i, j) <= adaptive.threshold)
+        });
+        if skip {
+            return;
+        }
+        let last = match settings.adaptive {
+            Some(_) => (first + settings.ns.max(1)).min(max_ns),
+            None => first + settings.ns.max(1),
+        };
+        let mut sampler = settings.sampler.for_pixel(settings.seed, i, j, max_ns);
+        job.add_samples(sampler.as_mut(), block, i, j, first..last);
+        added.fetch_add(last - first, Ordering::Relaxed);
+    });
+    added.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_same_pixels, spheres};
+
+    #[test]
+    fn thread_count_does_not_change_output() {
+        let world = spheres();
+        let camera = Camera::default();
+        let single = RenderSettings { nx: 40, ny: 20, ns: 4, adaptive: None, integrator: Integrator::default(),
+                                      filter: Filter::default(), sampler: SamplerKind::default(), seed: 7, threads: 1,
+                                      tile_size: 8 };
+        let multi = RenderSettings { threads: 4, tile_size: 5, ..single };
+        let a = render(&world, &HitableList::default(), &camera, &single);
+        let b = render(&world, &HitableList::default(), &camera, &multi);
+        assert_eq!(a.pixels().len(), 40 * 20);
+        assert_same_pixels(&a, &b);
+    }
+
+    #[test]
+    fn wide_filters_do_not_depend_on_thread_count() {
+        let world = spheres();
+        let camera = Camera::default();
+        let filter = Filter::named("lanczos").unwrap();
+        let single = RenderSettings { nx: 30, ny: 20, ns: 2, filter, seed: 3, threads: 1, tile_size: 4, ..RenderSettings::default() };
+        let multi = RenderSettings { threads: 4, ..single };
+        let a = render(&world, &HitableList::default(), &camera, &single);
+        let b = render(&world, &HitableList::default(), &camera, &multi);
+        assert_same_pixels(&a, &b);
+    }
+
+    #[test]
+    fn adaptive_sampling_spends_samples_where_the_noise_is() {
+        let world = spheres();
+        let camera = Camera::default();
+        let adaptive = AdaptiveSettings { threshold: 0.02, max_ns: 64 };
+        let settings = RenderSettings { nx: 40, ny: 20, ns: 4, adaptive: Some(adaptive), ..RenderSettings::default() };
+        let film = render_film(&world, &HitableList::default(), &camera, &settings, |_, _| {});
+        // The top rows see only sky, which a single sample gets right.
+        assert_eq!(film.samples(20, 0), 4);
+        let counts: Vec<u32> = (0..40).map(|i| film.samples(i, 12)).collect();
+        assert!(counts.iter().any(|&n| n > 4), "{:?}", counts);
+        assert!(counts.iter().all(|&n| n <= 64 && n % 4 == 0), "{:?}", counts);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::myvec::Vec3;
+use crate::qmc::{BlueNoiseSampler, HaltonSampler, SobolSampler, StratifiedSampler};
+
+/// Source of the random numbers a render consumes. Everything that needs
+/// randomness takes one of these, so a render is reproducible from its seed.
+pub trait Sampler {
+    /// Uniform random number in `[0, 1)`.
+    fn next_f32(&mut self) -> f32;
+
+    /// Uniform random number in `[min, max)`.
+    fn next_range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Moves on to sample `index` of the pixel. Samplers that spread the
+    /// samples of a pixel evenly start again at their first dimension;
+    /// independent random numbers just carry on.
+    fn start_sample(&mut self, _index: usize) {}
+}
+
+/// Independent random numbers from a `Pcg32` that starts afresh for every
+/// sample, so any sample of a pixel can be drawn without the ones before it.
+pub struct IndependentSampler {
+    seed: u64,
+    stream: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64, i: usize, j: usize) -> Self {
+        let stream = ((j as u64) << 32) | i as u64;
+        IndependentSampler { seed, stream, rng: Pcg32::new(seed, stream) }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn next_f32(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        let seed = self.seed.wrapping_add((index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        self.rng = Pcg32::new(seed, self.stream);
+    }
+}
+
+/// Which sampler a render draws its pixel, lens and scattering samples from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SamplerKind {
+    /// Independent random numbers.
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerKind {
+    /// `independent`, `stratified`, `halton`, `sobol` or `blue-noise`.
+    pub fn named(name: &str) -> Option<SamplerKind> {
+        match name {
+            "independent" => Some(SamplerKind::Independent),
+            "stratified" => Some(SamplerKind::Stratified),
+            "halton" => Some(SamplerKind::Halton),
+            "sobol" => Some(SamplerKind::Sobol),
+            "blue-noise" => Some(SamplerKind::BlueNoise),
+            _ => None,
+        }
+    }
+
+    /// The sampler for pixel `(i, j)` of a render seeded with `seed` that
+    /// takes `spp` samples per pixel.
+    pub fn for_pixel(self, seed: u64, i: usize, j: usize, spp: usize) -> Box<dyn Sampler> {
+        let pixel_seed = || {
+            let mut rng = Pcg32::for_pixel(seed, i, j);
+            u64::from(rng.next_u32()) << 32 | u64::from(rng.next_u32())
+        };
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed, i, j)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(pixel_seed(), spp)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(pixel_seed())),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(pixel_seed())),
+            // One scramble for the whole image; the blue noise varies it.
+            SamplerKind::BlueNoise => Box::new(BlueNoiseSampler::new(seed, i, j)),
+        }
+    }
+}
+
+/// PCG32 (XSH RR) generator by Melissa O'Neill.
+#[derive(Debug, Clone)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+impl Pcg32 {
+    /// Generators with different `stream`s produce independent sequences
+    /// even for the same `seed`.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 { state: 0, inc: (stream << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// The generator for pixel `(i, j)` of a render seeded with `seed`.
+    pub fn for_pixel(seed: u64, i: usize, j: usize) -> Self {
+        Pcg32::new(seed, ((j as u64) << 32) | i as u64)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+}
+
+impl Sampler for Pcg32 {
+    fn next_f32(&mut self) -> f32 {
+        // The top 24 bits fill an f32 mantissa exactly, so 1.0 is never returned.
+        (self.next_u32() >> 8) as f32 * (1.0 / 16_777_216.0)
+    }
+}
+
+pub fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Vec3 {
+    loop {
+        let x = sampler.next_range(-1.0, 1.0);
+        let y = sampler.next_range(-1.0, 1.0);
+        let z = sampler.next_range(-1.0, 1.0);
+        let p = Vec3::new(x,y,z);
+        if p.length() < 1.0 {
+            return p;
+        }
+    }
+}
+
+/// Uniformly distributed direction.
+pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Vec3 {
+    let z = 1.0 - 2.0 * sampler.next_f32();
+    let phi = 2.0 * std::f32::consts::PI * sampler.next_f32();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Uniform point in the unit disk in the `z = 0` plane, from exactly two
+/// numbers with Shirley and Chiu's concentric mapping, which keeps evenly
+/// spread samples evenly spread.
+pub fn sample_unit_disk(sampler: &mut dyn Sampler) -> Vec3 {
+    let a = 2.0 * sampler.next_f32() - 1.0;
+    let b = 2.0 * sampler.next_f32() - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return Vec3::default();
+    }
+    let quarter = std::f32::consts::FRAC_PI_4;
+    let (r, theta) = if a.abs() > b.abs() { (a, quarter * (b / a)) } else { (b, 2.0 * quarter - quarter * (a / b)) };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Vec3 {
+    loop {
+        let x = sampler.next_f32();
+        let y = sampler.next_f32();
+        let p = Vec3::new(x, y, 0.0) * 2.0 - Vec3::new(1., 1., 0.0);
+        if p.dot(p) < 1.0 {
+            return p;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcg32_matches_reference_sequence() {
+        // First outputs of the reference pcg32-demo for seed 42, stream 54.
+        let mut rng = Pcg32::new(42, 54);
+        let expected = [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e];
+        for &value in expected.iter() {
+            assert_eq!(rng.next_u32(), value);
+        }
+    }
+
+    #[test]
+    fn independent_samples_do_not_depend_on_earlier_ones() {
+        let mut a = IndependentSampler::new(3, 1, 2);
+        let mut b = IndependentSampler::new(3, 1, 2);
+        a.start_sample(0);
+        a.next_f32();
+        a.start_sample(5);
+        b.start_sample(5);
+        assert_eq!(a.next_f32(), b.next_f32());
+    }
+
+    #[test]
+    fn pixels_get_different_sequences() {
+        let mut a = Pcg32::for_pixel(1, 0, 0);
+        let mut b = Pcg32::for_pixel(1, 1, 0);
+        assert_ne!(a.next_u32(), b.next_u32());
+    }
+}
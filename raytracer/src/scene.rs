@@ -0,0 +1,999 @@
+//! TOML scene descriptions.
+//!
+//! ```toml
+//! [camera]
+//! lookfrom = [13.0, 2.0, 3.0]
+//! lookat = [0.0, 0.0, 0.0]
+//! vfov = 20.0
+//! aperture = 0.1
+//! focus_dist = 10.0
+//!
+//! [render]
+//! nx = 200
+//! ny = 100
+//! ns = 10
+//! max_depth = 50
+//! roulette_depth = 5
+//! background = "sky"
+//! filter = "box"
+//! sampler = "independent"
+//! adaptive = { threshold = 0.02, max_ns = 160 }
+//! exposure = 0.0
+//! tonemap = "clamp"
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = [0.5, 0.5, 0.5]
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [0.0, -1000.0, 0.0]
+//! radius = 1000.0
+//! material = "ground"
+//! ```
+//!
+//! `vup` defaults to `[0, 1, 0]`, `aperture` to 0 and `focus_dist` to the
+//! distance between `lookfrom` and `lookat`. `shutter_open` and
+//! `shutter_close` (both default 0) set the interval over which rays are
+//! spread for motion blur. Every `[render]` key is optional. Paths stop
+//! after `max_depth` bounces, and from `roulette_depth` bounces on Russian
+//! roulette ends dim paths early. `background` is either `"sky"` or a
+//! constant `[r, g, b]` color, and
+//! `fog = { color = [r, g, b], density = d }` fills the whole scene with
+//! homogeneous fog that hides distant objects and the background.
+//! `filter` weighs every sample into the pixels around it: `"box"` (the
+//! default, plain averaging), `"tent"`, `"gaussian"`, `"mitchell"` or
+//! `"lanczos"`, each with its usual radius unless `filter_radius` sets one
+//! (in pixels, greater than zero).
+//! `sampler` picks where the random numbers come from: `"independent"`
+//! (the default), or the evenly spread `"stratified"`, `"halton"`,
+//! `"sobol"` or `"blue-noise"`, which converge faster. With `adaptive`,
+//! pixels take `ns` more samples at a time while their estimated relative
+//! error is above `threshold`, up to `max_ns` (default 16 times `ns`).
+//!
+//! The rest of `[render]` decides how the image is written. `exposure`
+//! brightens it by that many stops, and `tonemap` is one of `"clamp"`,
+//! `"reinhard"`, `"reinhard-extended"` (`"reinhard-extended=8"` makes a
+//! radiance of 8 white instead of 4), `"aces"` or `"uncharted2"`. `dither = true`
+//! adds grain before 8-bit images are rounded, hiding banding.
+//!
+//! Objects are `sphere`s (`center`, `radius`), `moving_sphere`s (`center0`
+//! at `time0`, default 0, moving to `center1` at `time1`, default 1),
+//! `keyframed_sphere`s (`keyframes = [[time, x, y, z], ...]` sorted by time),
+//! `xy_rect`s (`x0`, `x1`, `y0`,
+//! `y1` and the plane `k`, likewise `xz_rect` and `yz_rect`, with an optional
+//! `flip = true` to turn the normal around), `box`es (`min`, `max`) or
+//! `mesh`es loaded from a Wavefront OBJ `path`, relative to the scene file.
+//! A mesh's `material` is optional and only used for faces that the OBJ's
+//! own MTL library does not cover, including those naming a material it
+//! lacks, which is reported in `Scene::warnings`.
+//!
+//! Boxes and meshes take an optional `transform = { scale = [x, y, z],
+//! rotate = [x, y, z], translate = [x, y, z] }`, applied in that order with
+//! rotations in degrees around x, then y, then z. Every mesh entry with the
+//! same `path` and `material` shares one copy of the triangles.
+//!
+//! A `constant_medium` is smoke of a given `density` and `albedo` (a color
+//! or texture name) filling a `boundary`, which is an inline `sphere`
+//! (`center`, `radius`) or `box` (`min`, `max`, `transform`) without a
+//! material:
+//!
+//! ```toml
+//! [[objects]]
+//! type = "constant_medium"
+//! density = 0.2
+//! albedo = [0.9, 0.9, 0.9]
+//! boundary = { type = "sphere", center = [0.0, 1.0, 0.0], radius = 1.0 }
+//! ```
+//!
+//! Besides `lambertian`, `metal` and `dielectric`, materials may be
+//! `diffuse_light` with an `emit = [r, g, b]` color, usually combined with a
+//! black background. Spheres and rectangles made of a `diffuse_light` are
+//! also sampled directly as lights, as are mesh faces whose material glows,
+//! whether it comes from the MTL library or the mesh's `material`.
+//!
+//! The `albedo` of `lambertian` and `metal` is either a color or the name of
+//! a texture:
+//!
+//! ```toml
+//! [textures.floor]
+//! type = "checker"
+//! odd = [0.2, 0.3, 0.1]
+//! even = [0.9, 0.9, 0.9]
+//! size = 0.5
+//!
+//! [textures.earth]
+//! type = "image"
+//! path = "earthmap.png"
+//! wrap = "repeat"
+//! ```
+//!
+//! Textures are `constant` (`color`), `checker` (`odd`, `even` and the cube
+//! `size`, default 1) or `image` (a PPM or PNG `path` relative to the scene
+//! file, and `wrap` = `repeat`, `mirror` or `clamp`).
+//!
+//! The procedural textures `noise`, `turbulence`, `marble`, `wood`, `worley`
+//! and `voronoi` blend from `low` (default black) to `high` (default white)
+//! and take a `seed` (default 0), a `scale` applied to the hit point
+//! (default 1) and the number of turbulence `octaves` (default 7).
+
+use crate::bvh::BvhNode;
+use crate::camera::CameraSettings;
+use crate::film::Filter;
+use crate::hitable::{bouncing_spheres, cornell_box, cornell_smoke, random_scene, Hitable, HitableList, MovingSphere, Sphere};
+use crate::integrator::{Background, Fog, Integrator};
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::myvec::Vec3;
+use crate::noise::{NoiseTexture, Pattern};
+use crate::obj::load_obj;
+use crate::instance::TransformedInstance;
+use crate::medium::ConstantMedium;
+use crate::mesh::Triangle;
+use crate::rect::{Cuboid, FlipNormals, XYRect, XZRect, YZRect};
+use crate::render::{AdaptiveSettings, RenderSettings};
+use crate::sampler::{Pcg32, SamplerKind};
+use crate::texture::{CheckerTexture, ConstantTexture, ImageTexture, Texture, WrapMode};
+use crate::tonemap::{OutputSettings, ToneMap};
+use crate::transform::Transform;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use toml::Spanned;
+
+pub struct Scene {
+    pub world: HitableList,
+    /// The objects of `world` that glow and can be sampled as lights.
+    pub lights: HitableList,
+    pub camera: CameraSettings,
+    pub settings: RenderSettings,
+    pub output: OutputSettings,
+    /// Problems that did not stop the scene from loading, such as mesh
+    /// faces naming a material their MTL library lacks.
+    pub warnings: Vec<String>,
+}
+
+impl Scene {
+    /// A scene defined in code rather than in a file. `seed` drives any
+    /// random placement.
+    pub fn builtin(name: &str, seed: u64) -> Option<Scene> {
+        match name {
+            "random" | "bouncing" => {
+                let settings = RenderSettings { seed, ..RenderSettings::default() };
+                let mut sampler = Pcg32::new(seed, 0);
+                let (world, aperture, shutter_close) = if name == "random" {
+                    (random_scene(&mut sampler), 0.1, 0.0)
+                } else {
+                    (bouncing_spheres(&mut sampler), 0.0, 1.0)
+                };
+                let camera = CameraSettings {
+                    lookfrom: Vec3::new(13.0, 2.0, 3.0),
+                    lookat: Vec3::new(0., 0., 0.),
+                    vup: Vec3::new(0., 1., 0.),
+                    vfov: 20.0,
+                    aperture,
+                    focus_dist: 10.0,
+                    shutter_open: 0.0,
+                    shutter_close,
+                };
+                Some(Scene { world, lights: HitableList::default(), camera, settings, output: OutputSettings::default(), warnings: Vec::new() })
+            }
+            "cornell" | "smoke" => {
+                let integrator = Integrator { background: Background::Solid(Vec3::default()), ..Integrator::default() };
+                let settings = RenderSettings { nx: 300, ny: 300, ns: 100, integrator, seed, ..RenderSettings::default() };
+                let camera = CameraSettings {
+                    lookfrom: Vec3::new(278.0, 278.0, -800.0),
+                    lookat: Vec3::new(278.0, 278.0, 0.0),
+                    vup: Vec3::new(0., 1., 0.),
+                    vfov: 40.0,
+                    aperture: 0.0,
+                    focus_dist: 10.0,
+                    shutter_open: 0.0,
+                    shutter_close: 0.0,
+                };
+                let (world, lights) = if name == "cornell" { cornell_box() } else { cornell_smoke() };
+                Some(Scene { world, lights, camera, settings, output: OutputSettings::default(), warnings: Vec::new() })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    /// Problem in the scene text; `line` and `column` are 1-based.
+    Parse { line: usize, column: usize, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{}", err),
+            SceneError::Parse { line, column, message } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<SceneError> for io::Error {
+    fn from(err: SceneError) -> Self {
+        match err {
+            SceneError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err.to_string()),
+        }
+    }
+}
+
+type V3 = [f32; 3];
+
+fn vec3(v: V3) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    camera: CameraDesc,
+    #[serde(default)]
+    render: RenderDesc,
+    #[serde(default)]
+    textures: HashMap<String, Spanned<TextureDesc>>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDesc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    lookfrom: V3,
+    lookat: V3,
+    #[serde(default = "default_vup")]
+    vup: V3,
+    vfov: f32,
+    #[serde(default)]
+    aperture: f32,
+    focus_dist: Option<f32>,
+    #[serde(default)]
+    shutter_open: f32,
+    #[serde(default)]
+    shutter_close: f32,
+}
+
+fn default_vup() -> V3 {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderDesc {
+    nx: Option<Spanned<usize>>,
+    ny: Option<Spanned<usize>>,
+    ns: Option<Spanned<usize>>,
+    max_depth: Option<usize>,
+    roulette_depth: Option<usize>,
+    seed: Option<u64>,
+    background: Option<BackgroundDesc>,
+    fog: Option<FogDesc>,
+    filter: Option<Spanned<String>>,
+    filter_radius: Option<Spanned<f32>>,
+    sampler: Option<Spanned<String>>,
+    adaptive: Option<AdaptiveDesc>,
+    exposure: Option<f32>,
+    tonemap: Option<Spanned<String>>,
+    dither: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AdaptiveDesc {
+    threshold: Spanned<f32>,
+    max_ns: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FogDesc {
+    color: V3,
+    density: Spanned<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BackgroundDesc {
+    Named(Named),
+    Solid(V3),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Named {
+    Sky,
+}
+
+impl BackgroundDesc {
+    fn build(&self) -> Background {
+        match *self {
+            BackgroundDesc::Named(Named::Sky) => Background::Sky,
+            BackgroundDesc::Solid(c) => Background::Solid(vec3(c)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Constant { color: V3 },
+    Checker { odd: V3, even: V3, #[serde(default = "default_checker_size")] size: f32 },
+    Image { path: String, #[serde(default)] wrap: WrapDesc },
+    Noise(NoiseDesc),
+    Turbulence(NoiseDesc),
+    Marble(NoiseDesc),
+    Wood(NoiseDesc),
+    Worley(NoiseDesc),
+    Voronoi(NoiseDesc),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoiseDesc {
+    #[serde(default)]
+    seed: u64,
+    #[serde(default = "default_noise_scale")]
+    scale: f32,
+    #[serde(default = "default_octaves")]
+    octaves: usize,
+    #[serde(default)]
+    low: V3,
+    #[serde(default = "default_high")]
+    high: V3,
+}
+
+fn default_noise_scale() -> f32 {
+    1.0
+}
+
+fn default_octaves() -> usize {
+    7
+}
+
+fn default_high() -> V3 {
+    [1.0, 1.0, 1.0]
+}
+
+impl NoiseDesc {
+    fn build(&self, pattern: Pattern) -> Arc<dyn Texture> {
+        Arc::new(NoiseTexture::new(pattern, self.seed, self.scale, self.octaves, vec3(self.low), vec3(self.high)))
+    }
+}
+
+fn default_checker_size() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum WrapDesc {
+    #[default]
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+/// A color written inline, or the name of a texture.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorDesc {
+    Color(V3),
+    Texture(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian { albedo: ColorDesc },
+    Metal { albedo: ColorDesc, #[serde(default)] fuzz: f32 },
+    Dielectric { ref_idx: f32 },
+    DiffuseLight { emit: V3 },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere { center: V3, radius: f32, material: String },
+    MovingSphere {
+        center0: V3,
+        center1: V3,
+        #[serde(default)] time0: f32,
+        #[serde(default = "default_time1")] time1: f32,
+        radius: f32,
+        material: String,
+    },
+    KeyframedSphere { keyframes: Vec<[f32; 4]>, radius: f32, material: String },
+    XyRect { x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: String, #[serde(default)] flip: bool },
+    XzRect { x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: String, #[serde(default)] flip: bool },
+    YzRect { y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: String, #[serde(default)] flip: bool },
+    Box { min: V3, max: V3, material: String, transform: Option<TransformDesc> },
+    Mesh { path: String, material: Option<String>, transform: Option<TransformDesc> },
+    ConstantMedium { boundary: BoundaryDesc, density: f32, albedo: ColorDesc },
+}
+
+/// The closed shape a `constant_medium` fills. It needs no material.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BoundaryDesc {
+    Sphere { center: V3, radius: f32 },
+    Box { min: V3, max: V3, transform: Option<TransformDesc> },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDesc {
+    #[serde(default = "default_scale")]
+    scale: V3,
+    /// Degrees around x, then y, then z.
+    #[serde(default)]
+    rotate: V3,
+    #[serde(default)]
+    translate: V3,
+}
+
+fn default_scale() -> V3 {
+    [1.0, 1.0, 1.0]
+}
+
+impl TransformDesc {
+    fn build(&self) -> Transform {
+        Transform::translate(vec3(self.translate))
+            * Transform::rotate_z(self.rotate[2])
+            * Transform::rotate_y(self.rotate[1])
+            * Transform::rotate_x(self.rotate[0])
+            * Transform::scale(vec3(self.scale))
+    }
+}
+
+fn placed(object: Arc<dyn Hitable>, transform: &Option<TransformDesc>) -> Box<dyn Hitable> {
+    let transform = transform.as_ref().map_or_else(Transform::identity, TransformDesc::build);
+    Box::new(TransformedInstance::new(object, transform))
+}
+
+fn default_time1() -> f32 {
+    1.0
+}
+
+fn flipped(hitable: Box<dyn Hitable>, flip: bool) -> Box<dyn Hitable> {
+    if flip {
+        Box::new(FlipNormals::new(hitable))
+    } else {
+        hitable
+    }
+}
+
+struct LoadedMesh {
+    bvh: Arc<dyn Hitable>,
+    /// The triangles whose material glows, in the mesh's own space.
+    emitters: Vec<Arc<Triangle>>,
+}
+
+struct Builder<'a> {
+    text: &'a str,
+    dir: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    /// Meshes already loaded, by path and material, so that placing the same
+    /// file many times shares one copy.
+    meshes: HashMap<(String, Option<String>), LoadedMesh>,
+    /// Names of the `diffuse_light` materials.
+    emitters: HashSet<String>,
+    lights: HitableList,
+    warnings: Vec<String>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(text: &'a str, dir: &'a Path) -> Self {
+        Builder {
+            text,
+            dir,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            meshes: HashMap::new(),
+            emitters: HashSet::new(),
+            lights: HitableList::default(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn error(&self, offset: usize, message: String) -> SceneError {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        SceneError::Parse { line, column, message }
+    }
+
+    fn texture(&self, desc: &Spanned<TextureDesc>) -> Result<Arc<dyn Texture>, SceneError> {
+        let constant = |c| -> Arc<dyn Texture> { Arc::new(ConstantTexture::new(vec3(c))) };
+        match *desc.get_ref() {
+            TextureDesc::Constant { color } => Ok(constant(color)),
+            TextureDesc::Checker { odd, even, size } => Ok(Arc::new(CheckerTexture::new(constant(odd), constant(even), size))),
+            TextureDesc::Image { ref path, wrap } => {
+                let wrap = match wrap {
+                    WrapDesc::Repeat => WrapMode::Repeat,
+                    WrapDesc::Mirror => WrapMode::Mirror,
+                    WrapDesc::Clamp => WrapMode::Clamp,
+                };
+                let texture = ImageTexture::load(self.dir.join(path), wrap)
+                    .map_err(|err| self.error(desc.span().start, format!("{}: {}", path, err)))?;
+                Ok(Arc::new(texture))
+            }
+            TextureDesc::Noise(ref noise) => Ok(noise.build(Pattern::Noise)),
+            TextureDesc::Turbulence(ref noise) => Ok(noise.build(Pattern::Turbulence)),
+            TextureDesc::Marble(ref noise) => Ok(noise.build(Pattern::Marble)),
+            TextureDesc::Wood(ref noise) => Ok(noise.build(Pattern::Wood)),
+            TextureDesc::Worley(ref noise) => Ok(noise.build(Pattern::Worley)),
+            TextureDesc::Voronoi(ref noise) => Ok(noise.build(Pattern::Voronoi)),
+        }
+    }
+
+    fn color(&self, desc: &ColorDesc, offset: usize) -> Result<Arc<dyn Texture>, SceneError> {
+        match desc {
+            ColorDesc::Color(c) => Ok(Arc::new(ConstantTexture::new(vec3(*c)))),
+            ColorDesc::Texture(name) => match self.textures.get(name) {
+                Some(texture) => Ok(Arc::clone(texture)),
+                None => Err(self.error(offset, format!("unknown texture `{}`", name))),
+            },
+        }
+    }
+
+    fn build_material(&self, desc: &Spanned<MaterialDesc>) -> Result<Arc<dyn Material>, SceneError> {
+        let offset = desc.span().start;
+        Ok(match desc.get_ref() {
+            MaterialDesc::Lambertian { albedo } => Arc::new(Lambertian::textured(self.color(albedo, offset)?)),
+            MaterialDesc::Metal { albedo, fuzz } => Arc::new(Metal::textured(self.color(albedo, offset)?, *fuzz)),
+            MaterialDesc::Dielectric { ref_idx } => Arc::new(Dielectric::new(*ref_idx)),
+            MaterialDesc::DiffuseLight { emit } => Arc::new(DiffuseLight::new(vec3(*emit))),
+        })
+    }
+
+    fn material(&self, name: &str, offset: usize) -> Result<Arc<dyn Material>, SceneError> {
+        match self.materials.get(name) {
+            Some(material) => Ok(Arc::clone(material)),
+            None => Err(self.error(offset, format!("unknown material `{}`", name))),
+        }
+    }
+
+    /// Boxes `object`, also listing it among the lights if its material
+    /// glows.
+    fn lit<T: Hitable + 'static>(&mut self, object: T, material: &str) -> Box<dyn Hitable> {
+        if !self.emitters.contains(material) {
+            return Box::new(object);
+        }
+        let object = Arc::new(object);
+        self.lights.add(Box::new(Arc::clone(&object)));
+        Box::new(object)
+    }
+
+    fn object(&mut self, desc: &Spanned<ObjectDesc>) -> Result<Box<dyn Hitable>, SceneError> {
+        let offset = desc.span().start;
+        match desc.get_ref() {
+            ObjectDesc::Sphere { center, radius, material } => {
+                let sphere = Sphere::new(vec3(*center), *radius, self.material(material, offset)?);
+                Ok(self.lit(sphere, material))
+            }
+            ObjectDesc::MovingSphere { center0, center1, time0, time1, radius, material } => {
+                let material = self.material(material, offset)?;
+                Ok(Box::new(MovingSphere::new(vec3(*center0), vec3(*center1), *time0, *time1, *radius, material)))
+            }
+            ObjectDesc::KeyframedSphere { keyframes, radius, material } => {
+                if keyframes.is_empty() || keyframes.windows(2).any(|w| w[0][0] >= w[1][0]) {
+                    return Err(self.error(offset, String::from("keyframes must be non-empty and sorted by time")));
+                }
+                let keys = keyframes.iter().map(|k| (k[0], Vec3::new(k[1], k[2], k[3]))).collect();
+                Ok(Box::new(MovingSphere::keyframed(keys, *radius, self.material(material, offset)?)))
+            }
+            ObjectDesc::XyRect { x0, x1, y0, y1, k, material, flip } => {
+                let rect = XYRect::new(*x0, *x1, *y0, *y1, *k, self.material(material, offset)?);
+                Ok(flipped(self.lit(rect, material), *flip))
+            }
+            ObjectDesc::XzRect { x0, x1, z0, z1, k, material, flip } => {
+                let rect = XZRect::new(*x0, *x1, *z0, *z1, *k, self.material(material, offset)?);
+                Ok(flipped(self.lit(rect, material), *flip))
+            }
+            ObjectDesc::YzRect { y0, y1, z0, z1, k, material, flip } => {
+                let rect = YZRect::new(*y0, *y1, *z0, *z1, *k, self.material(material, offset)?);
+                Ok(flipped(self.lit(rect, material), *flip))
+            }
+            ObjectDesc::Box { min, max, material, transform: None } => {
+                Ok(Box::new(Cuboid::new(vec3(*min), vec3(*max), self.material(material, offset)?)))
+            }
+            ObjectDesc::Box { min, max, material, transform } => {
+                let cuboid = Cuboid::new(vec3(*min), vec3(*max), self.material(material, offset)?);
+                Ok(placed(Arc::new(cuboid), transform))
+            }
+            ObjectDesc::Mesh { path, material: name, transform } => {
+                let key = (path.clone(), name.clone());
+                if !self.meshes.contains_key(&key) {
+                    let material = match name {
+                        Some(name) => self.material(name, offset)?,
+                        None => Arc::new(Lambertian::new(Vec3::new(0.73, 0.73, 0.73))),
+                    };
+                    let obj = load_obj(self.dir.join(path), material)
+                        .map_err(|err| self.error(offset, format!("{}: {}", path, err)))?;
+                    self.warnings.extend(obj.warnings.iter().map(|warning| format!("{}: {}", path, warning)));
+                    // Triangles never move, so the shutter interval does not matter.
+                    let bvh: Arc<dyn Hitable> = Arc::new(BvhNode::new(obj.triangles, 0.0, 0.0));
+                    self.meshes.insert(key.clone(), LoadedMesh { bvh, emitters: obj.emitters });
+                }
+                let transform = transform.as_ref().map_or_else(Transform::identity, TransformDesc::build);
+                let mesh = &self.meshes[&key];
+                for triangle in mesh.emitters.iter() {
+                    self.lights.add(Box::new(triangle.transformed(&transform)));
+                }
+                Ok(Box::new(TransformedInstance::new(Arc::clone(&mesh.bvh), transform)))
+            }
+            ObjectDesc::ConstantMedium { boundary, density, albedo } => {
+                // The boundary's own material is never seen, so any will do.
+                let unused: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3::default()));
+                let boundary: Box<dyn Hitable> = match boundary {
+                    BoundaryDesc::Sphere { center, radius } => Box::new(Sphere::new(vec3(*center), *radius, unused)),
+                    BoundaryDesc::Box { min, max, transform } => placed(Arc::new(Cuboid::new(vec3(*min), vec3(*max), unused)), transform),
+                };
+                if !(density.is_finite() && *density > 0.0) {
+                    return Err(self.error(offset, format!("medium density must be finite and positive, not {}", density)));
+                }
+                Ok(Box::new(ConstantMedium::textured(boundary, *density, self.color(albedo, offset)?)))
+            }
+        }
+    }
+}
+
+/// Parses a scene from TOML text. Mesh paths are relative to the current
+/// directory.
+pub fn parse_scene(text: &str) -> Result<Scene, SceneError> {
+    parse_scene_in(text, Path::new(""))
+}
+
+fn parse_scene_in(text: &str, dir: &Path) -> Result<Scene, SceneError> {
+    let desc: SceneDesc = toml::from_str(text).map_err(|err| {
+        let offset = err.span().map(|span| span.start).unwrap_or(0);
+        let builder = Builder::new(text, dir);
+        builder.error(offset, err.message().to_string())
+    })?;
+
+    // Build in file order so that the first of several errors is reported.
+    let mut textures: Vec<_> = desc.textures.iter().collect();
+    textures.sort_by_key(|(_, texture)| texture.span().start);
+    let mut materials: Vec<_> = desc.materials.iter().collect();
+    materials.sort_by_key(|(_, material)| material.span().start);
+
+    let mut builder = Builder::new(text, dir);
+    for (name, texture) in textures {
+        let texture = builder.texture(texture)?;
+        builder.textures.insert(name.clone(), texture);
+    }
+    for (name, material) in materials {
+        if let MaterialDesc::DiffuseLight { .. } = material.get_ref() {
+            builder.emitters.insert(name.clone());
+        }
+        let material = builder.build_material(material)?;
+        builder.materials.insert(name.clone(), material);
+    }
+
+    let mut world = HitableList::default();
+    for object in desc.objects.iter() {
+        world.add(builder.object(object)?);
+    }
+
+    let defaults = RenderSettings::default();
+    let render = desc.render;
+    let default_output = OutputSettings::default();
+    let tone_map = match render.tonemap {
+        Some(name) => ToneMap::parse(name.get_ref()).map_err(|message| builder.error(name.span().start, message))?,
+        None => default_output.tone_map,
+    };
+    let output = OutputSettings {
+        exposure: render.exposure.unwrap_or(default_output.exposure),
+        tone_map,
+        dither: render.dither.unwrap_or(default_output.dither),
+    };
+    let fog = match render.fog {
+        Some(fog) if !(fog.density.get_ref().is_finite() && *fog.density.get_ref() > 0.0) => {
+            let message = format!("fog density must be finite and positive, not {}", fog.density.get_ref());
+            return Err(builder.error(fog.density.span().start, message));
+        }
+        Some(fog) => Some(Fog { color: vec3(fog.color), density: fog.density.into_inner() }),
+        None => None,
+    };
+    let integrator = Integrator {
+        max_depth: render.max_depth.unwrap_or(defaults.integrator.max_depth),
+        roulette_depth: render.roulette_depth.unwrap_or(defaults.integrator.roulette_depth),
+        background: render.background.map_or(defaults.integrator.background, |b| b.build()),
+        fog,
+    };
+    let mut filter = match render.filter {
+        Some(name) => Filter::named(name.get_ref()).ok_or_else(|| {
+            builder.error(name.span().start, format!("unknown filter `{}`", name.get_ref()))
+        })?,
+        None => defaults.filter,
+    };
+    if let Some(radius) = render.filter_radius {
+        filter = filter.with_radius(*radius.get_ref()).ok_or_else(|| {
+            builder.error(radius.span().start, format!("filter radius must be finite and positive, not {}", radius.get_ref()))
+        })?;
+    }
+    let sampler = match render.sampler {
+        Some(name) => SamplerKind::named(name.get_ref()).ok_or_else(|| {
+            builder.error(name.span().start, format!("unknown sampler `{}`", name.get_ref()))
+        })?,
+        None => defaults.sampler,
+    };
+    let count = |value: Option<Spanned<usize>>, key: &str, default: usize| match value {
+        Some(value) if *value.get_ref() == 0 => Err(builder.error(value.span().start, format!("`{}` must be at least 1", key))),
+        Some(value) => Ok(value.into_inner()),
+        None => Ok(default),
+    };
+    let ns = count(render.ns, "ns", defaults.ns)?;
+    let adaptive = match render.adaptive {
+        Some(adaptive) => {
+            let threshold = *adaptive.threshold.get_ref();
+            if !(threshold.is_finite() && threshold >= 0.0) {
+                let message = format!("adaptive threshold must be finite and not negative, not {}", threshold);
+                return Err(builder.error(adaptive.threshold.span().start, message));
+            }
+            Some(AdaptiveSettings { threshold, max_ns: adaptive.max_ns.unwrap_or(16 * ns) })
+        }
+        None => None,
+    };
+    let settings = RenderSettings {
+        nx: count(render.nx, "nx", defaults.nx)?,
+        ny: count(render.ny, "ny", defaults.ny)?,
+        ns,
+        adaptive,
+        integrator,
+        filter,
+        sampler,
+        seed: render.seed.unwrap_or(defaults.seed),
+        ..defaults
+    };
+
+    let cam = desc.camera;
+    let lookfrom = vec3(cam.lookfrom);
+    let lookat = vec3(cam.lookat);
+    let camera = CameraSettings {
+        lookfrom,
+        lookat,
+        vup: vec3(cam.vup),
+        vfov: cam.vfov,
+        aperture: cam.aperture,
+        focus_dist: cam.focus_dist.unwrap_or_else(|| (lookfrom - lookat).length()),
+        shutter_open: cam.shutter_open,
+        shutter_close: cam.shutter_close,
+    };
+
+    Ok(Scene { world, lights: builder.lights, camera, settings, output, warnings: builder.warnings })
+}
+
+/// Reads and parses a scene file. Mesh paths are relative to the file.
+pub fn load_scene(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_scene_in(&fs::read_to_string(path)?, dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"
+[camera]
+lookfrom = [0.0, 0.0, 0.0]
+lookat = [0.0, 0.0, -1.0]
+vfov = 90.0
+
+[render]
+nx = 20
+ny = 10
+
+[materials.red]
+type = "lambertian"
+albedo = [0.8, 0.3, 0.3]
+
+[materials.glass]
+type = "dielectric"
+ref_idx = 1.5
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -1.0]
+radius = 0.5
+material = "red"
+
+[[objects]]
+type = "sphere"
+center = [1.0, 0.0, -1.0]
+radius = 0.5
+material = "glass"
+"#;
+
+    fn parse_error(text: &str) -> (usize, usize, String) {
+        match parse_scene(text) {
+            Err(SceneError::Parse { line, column, message }) => (line, column, message),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("scene should not parse"),
+        }
+    }
+
+    #[test]
+    fn loads_objects_and_settings() {
+        let scene = parse_scene(SCENE).unwrap();
+        assert_eq!(scene.world.length(), 2);
+        assert_eq!((scene.settings.nx, scene.settings.ny, scene.settings.ns), (20, 10, 10));
+    }
+
+    #[test]
+    fn unknown_material_reports_object_position() {
+        let text = SCENE.replace("material = \"glass\"", "material = \"gold\"");
+        let (line, column, message) = parse_error(&text);
+        assert_eq!((line, column), (25, 1));
+        assert!(message.contains("gold"), "{}", message);
+    }
+
+    #[test]
+    fn empty_images_are_rejected() {
+        for (from, to, line) in [("nx = 20", "nx = 0", 8), ("ny = 10", "ny = 0", 9), ("ny = 10", "ny = 10\nns = 0", 10)].iter() {
+            let (error_line, column, message) = parse_error(&SCENE.replace(from, to));
+            assert_eq!((error_line, column), (*line, 6));
+            assert!(message.contains("at least 1"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn syntax_error_reports_position() {
+        let text = SCENE.replace("radius = 0.5\nmaterial = \"red\"", "radius = 0.5,\nmaterial = \"red\"");
+        let (line, _, _) = parse_error(&text);
+        assert_eq!(line, 22);
+    }
+
+    #[test]
+    fn background_is_sky_or_a_color() {
+        let scene = parse_scene(SCENE).unwrap();
+        assert!(matches!(scene.settings.integrator.background, Background::Sky));
+
+        let text = SCENE.replace("ny = 10\n", "ny = 10\nbackground = [0.0, 0.0, 0.0]\n");
+        let scene = parse_scene(&text).unwrap();
+        assert!(matches!(scene.settings.integrator.background, Background::Solid(c) if c.length() == 0.0));
+
+        let text = SCENE.replace("type = \"dielectric\"\nref_idx = 1.5", "type = \"diffuse_light\"\nemit = [4.0, 4.0, 4.0]");
+        assert_eq!(parse_scene(&text).unwrap().world.length(), 2);
+    }
+
+    #[test]
+    fn albedo_may_name_a_texture() {
+        let checker = "[textures.check]\ntype = \"checker\"\nodd = [0.0, 0.0, 0.0]\neven = [1.0, 1.0, 1.0]\n\n";
+        let text = SCENE.replace("[materials.red]", &format!("{}[materials.red]", checker))
+            .replace("albedo = [0.8, 0.3, 0.3]", "albedo = \"check\"");
+        assert_eq!(parse_scene(&text).unwrap().world.length(), 2);
+
+        let marble = "[textures.marble]\ntype = \"marble\"\nseed = 3\nscale = 4.0\n\n";
+        let text = SCENE.replace("[materials.red]", &format!("{}[materials.red]", marble))
+            .replace("albedo = [0.8, 0.3, 0.3]", "albedo = \"marble\"");
+        assert_eq!(parse_scene(&text).unwrap().world.length(), 2);
+
+        let text = SCENE.replace("albedo = [0.8, 0.3, 0.3]", "albedo = \"stripes\"");
+        let (line, _, message) = parse_error(&text);
+        assert_eq!(line, 11);
+        assert!(message.contains("stripes"), "{}", message);
+    }
+
+    #[test]
+    fn boxes_may_be_transformed() {
+        let block = "[[objects]]\ntype = \"box\"\nmin = [-0.5, -0.5, -0.5]\nmax = [0.5, 0.5, 0.5]\nmaterial = \"red\"\n";
+        let moved = format!("{}transform = {{ scale = [2.0, 1.0, 1.0], rotate = [0.0, 90.0, 0.0], translate = [0.0, 0.0, -3.0] }}\n", block);
+        let scene = parse_scene(&format!("{}\n{}", SCENE, moved)).unwrap();
+        // Scaled along x, then turned so the long side runs along z.
+        let r = crate::ray::Ray::new(Vec3::new(0.0, 5.0, -3.9), Vec3::new(0.0, -1.0, 0.0));
+        let rec = scene.world.hit(&r, 0.001, f32::MAX).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-4, "{}", rec.t);
+        assert!((rec.normal.y - 1.0).abs() < 1e-4);
+
+        let text = format!("{}\n{}", SCENE, moved.replace("translate", "offset"));
+        let (_, _, message) = parse_error(&text);
+        assert!(message.contains("offset"), "{}", message);
+    }
+
+    #[test]
+    fn glowing_mesh_faces_become_lights() {
+        let dir = std::env::temp_dir().join(format!("raytracer-scene-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("lamp.mtl"), "newmtl lamp\nKe 4 4 4\n").unwrap();
+        fs::write(dir.join("lamp.obj"), "mtllib lamp.mtl\nv 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\n\
+            usemtl lamp\nf 4 3 2 1\nusemtl missing\nf 1 2 3\n").unwrap();
+        let mesh = "[[objects]]\ntype = \"mesh\"\npath = \"lamp.obj\"\n";
+        let moved = format!("{}transform = {{ translate = [0.0, 2.0, 0.0] }}\n", mesh);
+        let scene = parse_scene_in(&format!("{}\n{}\n{}", SCENE, mesh, moved), &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let scene = scene.unwrap();
+
+        // Both placements of the quad, but not the face that fell back to
+        // the default material.
+        assert_eq!(scene.lights.length(), 4);
+        assert_eq!(scene.warnings, vec![String::from("lamp.obj: line 8: unknown material `missing`, using the default")]);
+        let mut sampler = Pcg32::new(0, 0);
+        let heights: Vec<f32> = (0..64).map(|_| scene.lights.random(Vec3::default(), &mut sampler).y).collect();
+        assert!(heights.iter().all(|&y| y == 0.0 || y == 2.0), "{:?}", heights);
+        assert!(heights.contains(&0.0) && heights.contains(&2.0));
+    }
+
+    #[test]
+    fn media_and_fog() {
+        let smoke = "[[objects]]\ntype = \"constant_medium\"\ndensity = 0.5\nalbedo = [0.9, 0.9, 0.9]\n\
+            boundary = { type = \"box\", min = [-1.0, -1.0, -4.0], max = [1.0, 1.0, -3.0] }\n";
+        let text = SCENE.replace("ny = 10\n", "ny = 10\nfog = { color = [0.7, 0.7, 0.7], density = 0.01 }\n");
+        let scene = parse_scene(&format!("{}\n{}", text, smoke)).unwrap();
+        assert_eq!(scene.world.length(), 3);
+        assert!(scene.settings.integrator.fog.is_some_and(|fog| fog.density == 0.01));
+
+        let (_, _, message) = parse_error(&format!("{}\n{}", SCENE, smoke.replace("min =", "material = \"red\", min =")));
+        assert!(message.contains("material"), "{}", message);
+
+        let (line, _, message) = parse_error(&format!("{}\n{}", SCENE, smoke.replace("0.5", "-0.5")));
+        assert!(message.contains("medium density"), "{}", message);
+        assert_eq!(line, SCENE.lines().count() + 2);
+        let (line, _, message) = parse_error(&text.replace("0.01", "nan"));
+        assert!(message.contains("fog density"), "{}", message);
+        assert_eq!(line, SCENE.lines().position(|l| l == "ny = 10").unwrap() + 2);
+    }
+
+    #[test]
+    fn output_settings() {
+        let text = SCENE.replace("ny = 10\n", "ny = 10\nexposure = 1.5\ntonemap = \"reinhard-extended=8\"\n");
+        let scene = parse_scene(&text).unwrap();
+        assert_eq!(scene.output.exposure, 1.5);
+        assert_eq!(scene.output.tone_map, ToneMap::ReinhardExtended { white: 8.0 });
+        assert_eq!(parse_scene(SCENE).unwrap().output, OutputSettings::default());
+
+        let (line, _, message) = parse_error(&SCENE.replace("ny = 10\n", "ny = 10\ntonemap = \"filmic\"\n"));
+        assert!(message.contains("filmic"), "{}", message);
+        assert_eq!(line, SCENE.lines().position(|l| l == "ny = 10").unwrap() + 2);
+    }
+
+    #[test]
+    fn sampling_settings() {
+        let text = SCENE.replace("ny = 10\n", "ny = 10\nfilter = \"gaussian\"\nfilter_radius = 2.5\nsampler = \"blue-noise\"\n");
+        let scene = parse_scene(&text).unwrap();
+        assert_eq!(scene.settings.filter, Filter::Gaussian { radius: 2.5, alpha: 2.0 });
+        assert_eq!(scene.settings.sampler, SamplerKind::BlueNoise);
+        assert_eq!(scene.settings.adaptive, None);
+
+        let text = SCENE.replace("ny = 10\n", "ny = 10\nns = 8\nadaptive = { threshold = 0.05 }\n");
+        let adaptive = parse_scene(&text).unwrap().settings.adaptive;
+        assert_eq!(adaptive, Some(AdaptiveSettings { threshold: 0.05, max_ns: 128 }));
+        for threshold in ["-0.05", "nan"].iter() {
+            let (line, _, message) = parse_error(&text.replace("0.05", threshold));
+            assert!(message.contains("adaptive threshold"), "{}", message);
+            assert_eq!(line, SCENE.lines().position(|l| l == "ny = 10").unwrap() + 3);
+        }
+
+        let (line, _, message) = parse_error(&SCENE.replace("ny = 10\n", "ny = 10\nfilter = \"sharp\"\n"));
+        assert!(message.contains("sharp"), "{}", message);
+        assert_eq!(line, SCENE.lines().position(|l| l == "ny = 10").unwrap() + 2);
+
+        for radius in ["0.0", "-1.5", "nan", "inf"].iter() {
+            let text = SCENE.replace("ny = 10\n", &format!("ny = 10\nfilter_radius = {}\n", radius));
+            let (line, _, message) = parse_error(&text);
+            assert!(message.contains("filter radius"), "{}", message);
+            assert_eq!(line, SCENE.lines().position(|l| l == "ny = 10").unwrap() + 2);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Fixtures shared by the tests of the rendering modules.
+
+use crate::film::Film;
+use crate::hitable::{HitableList, Sphere};
+use crate::image::Framebuffer;
+use crate::material::{Lambertian, Metal};
+use crate::myvec::Vec3;
+use std::sync::Arc;
+
+/// A diffuse sphere straight ahead of the default camera and a rough
+/// metal one to its right, on a yellow ground. Each is tagged so that
+/// AOV renders can tell them apart.
+pub(crate) fn spheres() -> HitableList {
+    let mut list = HitableList::default();
+    list.add(Box::new(Sphere::new(Vec3::new(0., -100.5, -1.), 100.,
+        Arc::new(Lambertian::new(Vec3::new(0.8, 0.8, 0.0))))));
+    list.add(Box::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5,
+        Arc::new(Lambertian::new(Vec3::new(0.1, 0.2, 0.5))))));
+    list.add(Box::new(Sphere::new(Vec3::new(1., 0., -1.), 0.5,
+        Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.3)))));
+    list.tagged()
+}
+
+/// Asserts that two images agree bit for bit.
+pub(crate) fn assert_same_pixels(a: &Framebuffer, b: &Framebuffer) {
+    assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+    for (p, q) in a.pixels().iter().zip(b.pixels().iter()) {
+        assert_eq!((p.x.to_bits(), p.y.to_bits(), p.z.to_bits()),
+                   (q.x.to_bits(), q.y.to_bits(), q.z.to_bits()));
+    }
+}
+
+/// Asserts that two films agree bit for bit, in their image and in the
+/// sample counts and error estimates of every pixel.
+pub(crate) fn assert_same_film(a: &Film, b: &Film) {
+    let (a_image, b_image) = (a.to_framebuffer(), b.to_framebuffer());
+    assert_same_pixels(&a_image, &b_image);
+    for j in 0..a_image.height() {
+        for i in 0..a_image.width() {
+            assert_eq!(a.samples(i, j), b.samples(i, j));
+            assert_eq!(a.relative_error(i, j).to_bits(), b.relative_error(i, j).to_bits());
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::image::{load_image, Framebuffer};
+use crate::myvec::Vec3;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Color that varies over a surface, looked up by the surface coordinates
+/// `(u, v)` of a hit and by its point `p` in space.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3;
+}
+
+/// The same color everywhere.
+pub struct ConstantTexture {
+    color: Vec3,
+}
+
+impl ConstantTexture {
+    pub fn new(color: Vec3) -> Self {
+        ConstantTexture { color }
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn value(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
+        self.color
+    }
+}
+
+/// Solid 3D checkerboard of cubes `size` wide, so the pattern does not
+/// depend on how the surface is parameterised.
+pub struct CheckerTexture {
+    odd: Arc<dyn Texture>,
+    even: Arc<dyn Texture>,
+    size: f32,
+}
+
+impl CheckerTexture {
+    pub fn new(odd: Arc<dyn Texture>, even: Arc<dyn Texture>, size: f32) -> Self {
+        CheckerTexture { odd, even, size }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        let cell = (p.x / self.size).floor() + (p.y / self.size).floor() + (p.z / self.size).floor();
+        if cell.rem_euclid(2.0) == 1.0 {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+}
+
+/// What an image texture shows outside `[0, 1]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Tile the image.
+    Repeat,
+    /// Tile the image, flipping every other copy so the seams match.
+    Mirror,
+    /// Stretch the edge texels outwards.
+    Clamp,
+}
+
+impl WrapMode {
+    fn wrap(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m < n { m } else { 2 * n - 1 - m }
+            }
+            WrapMode::Clamp => i.clamp(0, n - 1),
+        };
+        i as usize
+    }
+}
+
+/// Bilinearly filtered image. `(0, 0)` is the bottom-left corner and
+/// `(1, 1)` the top-right one.
+pub struct ImageTexture {
+    image: Framebuffer,
+    wrap: WrapMode,
+}
+
+impl ImageTexture {
+    pub fn new(image: Framebuffer, wrap: WrapMode) -> Self {
+        assert!(image.width() > 0 && image.height() > 0, "texture image is empty");
+        ImageTexture { image, wrap }
+    }
+
+    /// Loads a PPM or PNG file.
+    pub fn load(path: impl AsRef<Path>, wrap: WrapMode) -> io::Result<Self> {
+        let image = load_image(path)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "texture image is empty"));
+        }
+        Ok(ImageTexture::new(image, wrap))
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vec3 {
+        self.image.get(self.wrap.wrap(x, self.image.width()), self.wrap.wrap(y, self.image.height()))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _p: Vec3) -> Vec3 {
+        // Texel centres sit at half-integer positions.
+        let x = u * self.image.width() as f32 - 0.5;
+        let y = (1.0 - v) * self.image.height() as f32 - 0.5;
+        if !x.is_finite() || !y.is_finite() {
+            return Vec3::default();
+        }
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(wrap: WrapMode) -> ImageTexture {
+        // Two texels: black on the left, white on the right.
+        let image = Framebuffer::from_pixels(2, 1, vec![Vec3::new(0., 0., 0.), Vec3::new(1., 1., 1.)]);
+        ImageTexture::new(image, wrap)
+    }
+
+    #[test]
+    fn bilinear_filter_blends_neighbours() {
+        let texture = ramp(WrapMode::Clamp);
+        assert_eq!(texture.value(0.25, 0.5, Vec3::default()).x, 0.0);
+        assert_eq!(texture.value(0.5, 0.5, Vec3::default()).x, 0.5);
+        assert_eq!(texture.value(0.75, 0.5, Vec3::default()).x, 1.0);
+    }
+
+    #[test]
+    fn wrap_modes_differ_outside_the_image() {
+        let at = |wrap| ramp(wrap).value(1.25, 0.5, Vec3::default()).x;
+        assert_eq!(at(WrapMode::Repeat), 0.0);
+        assert_eq!(at(WrapMode::Mirror), 1.0);
+        assert_eq!(at(WrapMode::Clamp), 1.0);
+        assert_eq!(ramp(WrapMode::Mirror).value(1.75, 0.5, Vec3::default()).x, 0.0);
+    }
+
+    #[test]
+    fn checker_alternates_in_every_axis() {
+        let white: Arc<dyn Texture> = Arc::new(ConstantTexture::new(Vec3::new(1., 1., 1.)));
+        let black: Arc<dyn Texture> = Arc::new(ConstantTexture::new(Vec3::default()));
+        let checker = CheckerTexture::new(black, white, 0.5);
+        let at = |x, y, z| checker.value(0., 0., Vec3::new(x, y, z)).x;
+        assert_eq!(at(0.25, 0.25, 0.25), 1.0);
+        assert_eq!(at(0.75, 0.25, 0.25), 0.0);
+        assert_eq!(at(0.25, -0.25, 0.25), 0.0);
+        assert_eq!(at(0.75, 0.25, -0.25), 1.0);
+    }
+}
@@ -0,0 +1,211 @@
+//! Turning the linear, unbounded radiance of a render into display values:
+//! exposure, a tone mapping curve, the sRGB transfer function and
+//! quantization to 8 bits.
+
+use crate::myvec::Vec3;
+use crate::sampler::{Pcg32, Sampler};
+
+/// Curve squeezing radiance into `[0, 1]`. Each channel is mapped on its
+/// own, so very bright colors drift towards white.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ToneMap {
+    /// No curve: everything above 1 is clipped.
+    #[default]
+    Clamp,
+    /// `x / (1 + x)`, which never quite reaches white.
+    Reinhard,
+    /// Reinhard rescaled so that `white` and anything brighter maps to 1.
+    ReinhardExtended { white: f32 },
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve.
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2, with its white point of
+    /// 11.2 and exposure bias of 2.
+    Uncharted2,
+}
+
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+impl ToneMap {
+    /// Maps one linear channel value, which must not be negative.
+    pub fn map(&self, x: f32) -> f32 {
+        match *self {
+            ToneMap::Clamp => x,
+            ToneMap::Reinhard => x / (1.0 + x),
+            ToneMap::ReinhardExtended { white } => x * (1.0 + x / (white * white)) / (1.0 + x),
+            ToneMap::Aces => x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14),
+            ToneMap::Uncharted2 => hable(2.0 * x) / hable(11.2),
+        }
+    }
+
+    /// Reads a curve by name: `clamp`, `reinhard`, `reinhard-extended`
+    /// (white at 4) or `reinhard-extended=WHITE`, `aces` or `uncharted2`.
+    pub fn parse(s: &str) -> Result<ToneMap, String> {
+        match s {
+            "clamp" => Ok(ToneMap::Clamp),
+            "reinhard" => Ok(ToneMap::Reinhard),
+            "reinhard-extended" => Ok(ToneMap::ReinhardExtended { white: 4.0 }),
+            "aces" => Ok(ToneMap::Aces),
+            "uncharted2" => Ok(ToneMap::Uncharted2),
+            _ => match s.strip_prefix("reinhard-extended=") {
+                Some(white) => match white.parse::<f32>() {
+                    Ok(white) if white.is_finite() && white > 0.0 => Ok(ToneMap::ReinhardExtended { white }),
+                    Ok(white) => Err(format!("white point must be finite and positive, not {}", white)),
+                    Err(_) => Err(format!("`{}` is not a number", white)),
+                },
+                None => Err(format!("unknown tone mapping `{}`", s)),
+            },
+        }
+    }
+
+    pub fn apply(&self, c: Vec3) -> Vec3 {
+        Vec3::new(self.map(c.x), self.map(c.y), self.map(c.z))
+    }
+}
+
+/// The sRGB opto-electronic transfer function: linear `[0, 1]` to the
+/// encoded values stored in 8-bit images.
+pub fn srgb_encode(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse of `srgb_encode`.
+pub fn srgb_decode(encoded: f32) -> f32 {
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// How a linear image becomes 8-bit sRGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputSettings {
+    /// Brightness adjustment in stops: each one doubles the radiance
+    /// before tone mapping.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+    /// Adds triangular noise of up to one step before rounding, which trades
+    /// banding in smooth gradients for fine grain.
+    pub dither: bool,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        OutputSettings { exposure: 0.0, tone_map: ToneMap::Clamp, dither: false }
+    }
+}
+
+impl OutputSettings {
+    /// Exposed and tone mapped color, still linear and clamped to `[0, 1]`.
+    /// NaNs become black.
+    pub fn display(&self, c: Vec3) -> Vec3 {
+        let scale = self.exposure.exp2();
+        let channel = |x: f32| {
+            // Infinities are capped where every curve is still finite.
+            let x = x * scale;
+            let x = if x.is_nan() { 0.0 } else { x.clamp(0.0, 1e6) };
+            self.tone_map.map(x).clamp(0.0, 1.0)
+        };
+        Vec3::new(channel(c.x), channel(c.y), channel(c.z))
+    }
+
+    /// 8-bit sRGB triplets for `pixels`, in order.
+    pub fn quantize(&self, pixels: &[Vec3]) -> Vec<u8> {
+        // A fixed stream, so the same image always gets the same grain.
+        let mut rng = Pcg32::new(0, 0);
+        let mut bytes = Vec::with_capacity(pixels.len() * 3);
+        for col in pixels.iter() {
+            let col = self.display(*col);
+            for &c in [col.x, col.y, col.z].iter() {
+                let mut v = srgb_encode(c) * 255.0;
+                if self.dither {
+                    v += rng.next_f32() - rng.next_f32();
+                }
+                // The cast saturates, so dithered values stay in range.
+                bytes.push(v.round() as u8);
+            }
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [ToneMap; 5] = [
+        ToneMap::Clamp,
+        ToneMap::Reinhard,
+        ToneMap::ReinhardExtended { white: 4.0 },
+        ToneMap::Aces,
+        ToneMap::Uncharted2,
+    ];
+
+    #[test]
+    fn curves_start_at_black_and_never_darken() {
+        for curve in CURVES.iter() {
+            assert!(curve.map(0.0).abs() < 1e-6, "{:?}", curve);
+            let mut last = 0.0;
+            for i in 1..1000 {
+                let y = curve.map(i as f32 * 0.02);
+                assert!(y >= last, "{:?} falls at {}", curve, i as f32 * 0.02);
+                last = y;
+            }
+        }
+        assert!((ToneMap::ReinhardExtended { white: 4.0 }.map(4.0) - 1.0).abs() < 1e-6);
+        assert!((ToneMap::Uncharted2.map(5.6) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn curves_parse_by_name() {
+        assert_eq!(ToneMap::parse("aces"), Ok(ToneMap::Aces));
+        assert_eq!(ToneMap::parse("reinhard-extended"), Ok(ToneMap::ReinhardExtended { white: 4.0 }));
+        assert_eq!(ToneMap::parse("reinhard-extended=6"), Ok(ToneMap::ReinhardExtended { white: 6.0 }));
+        for bad in ["filmic", "reinhard_extended", "reinhard-extended=bright", "reinhard-extended=0"].iter() {
+            assert!(ToneMap::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+            assert!((srgb_decode(srgb_encode(c)) - c).abs() < 1e-5);
+        }
+        assert!((srgb_encode(0.5) - 0.735_36).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bright_and_invalid_values_clamp() {
+        let pixels = [Vec3::new(1e6, -3.0, f32::NAN), Vec3::new(1.0, 0.0, 0.5), Vec3::new(f32::INFINITY, 0.0, 0.0)];
+        for curve in CURVES.iter() {
+            let output = OutputSettings { tone_map: *curve, ..OutputSettings::default() };
+            let bytes = output.quantize(&pixels);
+            assert_eq!((bytes[0], bytes[1], bytes[2], bytes[6]), (255, 0, 0, 255), "{:?}", curve);
+        }
+        assert_eq!(OutputSettings::default().quantize(&pixels)[3..6], [255, 0, 188]);
+    }
+
+    #[test]
+    fn dithering_keeps_the_average() {
+        // Halfway between two steps, where rounding alone is always off by half.
+        let gray = vec![Vec3::new(0.214_04, 0.214_04, 0.214_04); 10_000];
+        let output = OutputSettings { dither: true, ..OutputSettings::default() };
+        let bytes = output.quantize(&gray);
+        let mean = bytes.iter().map(|&b| b as f32).sum::<f32>() / bytes.len() as f32;
+        assert!(bytes.iter().any(|&b| b != bytes[0]));
+        assert!((mean - srgb_encode(0.214_04) * 255.0).abs() < 0.05, "{}", mean);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::aabb::Aabb;
+use crate::myvec::Vec3;
+use std::ops;
+
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1., 0., 0., 0.],
+    [0., 1., 0., 0.],
+    [0., 0., 1., 0.],
+    [0., 0., 0., 1.],
+];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+fn transpose(a: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = a[j][i];
+        }
+    }
+    m
+}
+
+/// Affine 4x4 transform, kept together with its inverse so that neither
+/// ever has to be computed from the other.
+///
+/// `a * b` applies `b` first, then `a`.
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    m: Matrix,
+    inv: Matrix,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform { m: IDENTITY, inv: IDENTITY }
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut m = IDENTITY;
+        let mut inv = IDENTITY;
+        for axis in 0..3 {
+            m[axis][3] = offset[axis];
+            inv[axis][3] = -offset[axis];
+        }
+        Transform { m, inv }
+    }
+
+    /// Scales by `factors` along the axes. Every factor must be non-zero.
+    pub fn scale(factors: Vec3) -> Self {
+        let mut m = IDENTITY;
+        let mut inv = IDENTITY;
+        for axis in 0..3 {
+            m[axis][axis] = factors[axis];
+            inv[axis][axis] = 1.0 / factors[axis];
+        }
+        Transform { m, inv }
+    }
+
+    /// Rotation by `degrees` counter-clockwise around `axis`, looking down
+    /// the axis towards the origin.
+    pub fn rotate(axis: Vec3, degrees: f32) -> Self {
+        let a = axis.normalize();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut m = IDENTITY;
+        m[0][0] = a.x * a.x + (1.0 - a.x * a.x) * cos;
+        m[0][1] = a.x * a.y * (1.0 - cos) - a.z * sin;
+        m[0][2] = a.x * a.z * (1.0 - cos) + a.y * sin;
+        m[1][0] = a.x * a.y * (1.0 - cos) + a.z * sin;
+        m[1][1] = a.y * a.y + (1.0 - a.y * a.y) * cos;
+        m[1][2] = a.y * a.z * (1.0 - cos) - a.x * sin;
+        m[2][0] = a.x * a.z * (1.0 - cos) - a.y * sin;
+        m[2][1] = a.y * a.z * (1.0 - cos) + a.x * sin;
+        m[2][2] = a.z * a.z + (1.0 - a.z * a.z) * cos;
+        // Rotations are orthogonal, so the inverse is the transpose.
+        Transform { m, inv: transpose(&m) }
+    }
+
+    pub fn rotate_x(degrees: f32) -> Self {
+        Transform::rotate(Vec3::new(1., 0., 0.), degrees)
+    }
+
+    pub fn rotate_y(degrees: f32) -> Self {
+        Transform::rotate(Vec3::new(0., 1., 0.), degrees)
+    }
+
+    pub fn rotate_z(degrees: f32) -> Self {
+        Transform::rotate(Vec3::new(0., 0., 1.), degrees)
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform { m: self.inv, inv: self.m }
+    }
+
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    /// Transforms a direction, ignoring the translation.
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// Transforms a surface normal with the inverse transpose, which keeps
+    /// it perpendicular to the surface under non-uniform scaling. The result
+    /// is not normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let inv = &self.inv;
+        Vec3::new(
+            inv[0][0] * n.x + inv[1][0] * n.y + inv[2][0] * n.z,
+            inv[0][1] * n.x + inv[1][1] * n.y + inv[2][1] * n.z,
+            inv[0][2] * n.x + inv[1][2] * n.y + inv[2][2] * n.z,
+        )
+    }
+
+    /// Box enclosing the eight transformed corners of `bbox`.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        let mut result = Aabb::empty();
+        for corner in 0..8 {
+            let p = Vec3::new(
+                if corner & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                if corner & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                if corner & 4 == 0 { bbox.min.z } else { bbox.max.z },
+            );
+            let p = self.point(p);
+            result = result.union(&Aabb::new(p, p));
+        }
+        result
+    }
+}
+
+impl ops::Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform { m: multiply(&self.m, &rhs.m), inv: multiply(&rhs.inv, &self.inv) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-4
+    }
+
+    #[test]
+    fn composition_applies_right_operand_first() {
+        let t = Transform::translate(Vec3::new(1., 0., 0.)) * Transform::rotate_z(90.0);
+        assert!(close(t.point(Vec3::new(1., 0., 0.)), Vec3::new(1., 1., 0.)));
+        assert!(close(t.inverse().point(Vec3::new(1., 1., 0.)), Vec3::new(1., 0., 0.)));
+        assert!(close(t.vector(Vec3::new(1., 0., 0.)), Vec3::new(0., 1., 0.)));
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        let t = Transform::scale(Vec3::new(4., 1., 1.)) * Transform::rotate_y(30.0);
+        let tangent = Vec3::new(1., -1., 0.);
+        let normal = Vec3::new(1., 1., 0.);
+        assert!(t.vector(tangent).dot(t.normal(normal)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bounding_box_covers_rotated_corners() {
+        let bbox = Aabb::new(Vec3::new(-1., -1., -1.), Vec3::new(1., 1., 1.));
+        let rotated = Transform::rotate_y(45.0).bounding_box(&bbox);
+        let half_diagonal = 2f32.sqrt();
+        assert!((rotated.max.x - half_diagonal).abs() < 1e-5);
+        assert!((rotated.max.y - 1.0).abs() < 1e-5);
+    }
+}